@@ -0,0 +1,148 @@
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path;
+
+use crate::csv;
+use crate::history;
+use crate::history::Change;
+use crate::task::TaskId;
+
+/// Append-only record of every change made to the tasks, reverts and redos
+/// included.
+/// Unlike the [History](crate::history::History) it is never trimmed nor
+/// rolled back, so it tells everything that happened to a task.
+#[derive(Debug, Default)]
+pub struct Activity {
+    file: Option<String>,
+    /// Changes of a session only activity, persistent ones read their file
+    changes: Vec<Change>,
+}
+
+impl Activity {
+    /// Activity that only lasts for the current session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Activity kept in the given file, which other sessions append to too
+    pub fn persistent(file: &str) -> Self {
+        Self {
+            file: Some(String::from(file)),
+            ..Self::default()
+        }
+    }
+
+    /// Durably appends the given changes before returning
+    pub fn record(&mut self, changes: &[Change]) -> Result<(), io::Error> {
+        let file = match &self.file {
+            Some(f) => f,
+            None => {
+                self.changes.extend(changes.iter().cloned());
+                return Ok(());
+            },
+        };
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        for change in changes {
+            let fields = history::encode_change(change);
+            let fields: Vec<_> = fields.iter().map(|f| f.as_str()).collect();
+            content += format!("{}\n", csv::encode_record(&fields)).as_str();
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(file)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_data()
+    }
+
+    /// Changes made to the given task, oldest first, along with a warning for
+    /// every entry that could not be read
+    pub fn changes(&self, id: TaskId) -> Result<(Vec<Change>, Vec<String>), io::Error> {
+        let file = match &self.file {
+            Some(f) => f,
+            None => {
+                let changes = self.changes.iter().filter(|c| c.id == id).cloned();
+                return Ok((changes.collect(), vec![]));
+            },
+        };
+        if !path::Path::new(file).exists() {
+            return Ok((vec![], vec![]));
+        }
+
+        let mut changes = vec![];
+        let mut warnings = vec![];
+        for record in csv::parse(&fs::read_to_string(file)?) {
+            let change = match record.complete {
+                true => history::decode_change(&record.fields),
+                false => Err(String::from("missmatched parts")),
+            };
+
+            match change {
+                Ok(change) if change.id == id => changes.push(change),
+                Ok(_) => (),
+                Err(reason) => warnings.push(format!(
+                    "Ignoring activity entry '{}' due to {}",
+                    record.raw, reason
+                )),
+            }
+        }
+
+        Ok((changes, warnings))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::*;
+    use crate::date::Timestamp;
+    use crate::task::Task;
+
+    fn change(id: usize, before: Option<&str>, after: Option<&str>) -> Change {
+        let id = TaskId::new(id);
+        Change {
+            id,
+            before: before.map(|t| Task::new(id, t)),
+            after: after.map(|t| Task::new(id, t)),
+            at: Some(Timestamp::new(1_760_000_000)),
+        }
+    }
+
+    #[test]
+    fn it_appends_changes_and_reads_them_per_task() {
+        let file = env::temp_dir().join(format!("todo_list_{}_activity", std::process::id()));
+        let file = file.to_str().unwrap();
+        let _ = fs::remove_file(file);
+
+        let mut activity = Activity::persistent(file);
+        activity
+            .record(&[change(1, None, Some("a;\"b\"")), change(2, None, Some("c"))])
+            .unwrap();
+        Activity::persistent(file)
+            .record(&[change(1, Some("a;\"b\""), Some("d\ne"))])
+            .unwrap();
+        fs::write(file, fs::read_to_string(file).unwrap() + "oops\n").unwrap();
+
+        let (changes, warnings) = activity.changes(TaskId::new(1)).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                change(1, None, Some("a;\"b\"")),
+                change(1, Some("a;\"b\""), Some("d\ne")),
+            ]
+        );
+        assert_eq!(
+            warnings,
+            vec![String::from(
+                "Ignoring activity entry 'oops' due to missmatched parts"
+            )]
+        );
+
+        fs::remove_file(file).unwrap();
+    }
+}
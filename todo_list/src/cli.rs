@@ -0,0 +1,174 @@
+use std::env;
+
+/// Task file used when neither `--file` nor [FILE_ENV_VAR] are given
+pub const DEFAULT_TASK_FILE: &str = "tasks.csv";
+pub const FILE_ENV_VAR: &str = "TODO_FILE";
+
+/// Command line options.
+/// Without a command the interactive prompt is started.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Options {
+    pub file: String,
+    /// Print the tasks `list`, `find` and `remind` show as a single JSON
+    /// document, the messages being printed as usual
+    pub json: bool,
+    /// Leave out every notice, only printing tasks, warnings and errors
+    pub quiet: bool,
+    /// Print every message and task as a JSON object per line, as they come,
+    /// unlike [Options::json]
+    pub json_lines: bool,
+    /// Port `serve` listens on
+    pub port: Option<u16>,
+    pub command: Vec<String>,
+}
+
+impl Options {
+    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        args.next(); // We skip the first argument as it should be the program name
+
+        let mut file = env::var(FILE_ENV_VAR).ok().filter(|f| !f.is_empty());
+        let mut json = false;
+        let mut quiet = false;
+        let mut json_lines = false;
+        let mut port = None;
+        let mut command = vec![];
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--file" => match args.next() {
+                    Some(f) => file = Some(f),
+                    None => return Err(String::from("Missing value for --file")),
+                },
+                "--json" => json = true,
+                "--quiet" => quiet = true,
+                "--json-lines" => json_lines = true,
+                "--port" => match args.next() {
+                    Some(p) => port = Some(parse_port(&p)?),
+                    None => return Err(String::from("Missing value for --port")),
+                },
+                "--" => command.extend(args.by_ref()),
+                _ if arg.starts_with("--file=") => file = Some(String::from(&arg[7..])),
+                _ if arg.starts_with("--port=") => port = Some(parse_port(&arg[7..])?),
+                _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+                _ => command.push(arg),
+            }
+        }
+
+        Ok(Self {
+            file: file.unwrap_or(String::from(DEFAULT_TASK_FILE)),
+            json,
+            quiet,
+            json_lines,
+            port,
+            command,
+        })
+    }
+
+    /// The command as it would be typed in the interactive prompt, quoting
+    /// back the arguments the shell already unquoted
+    pub fn command_line(&self) -> Option<String> {
+        if self.command.is_empty() {
+            return None;
+        }
+
+        let args: Vec<_> = self.command.iter().map(|a| quote_arg(a)).collect();
+        Some(args.join(" "))
+    }
+}
+
+fn parse_port(input: &str) -> Result<u16, String> {
+    input
+        .parse()
+        .map_err(|_| format!("Invalid value for --port: {}", input))
+}
+
+fn quote_arg(arg: &str) -> String {
+    let special = |c: char| c.is_whitespace() || ['\'', '"', '\\'].contains(&c);
+    if !arg.is_empty() && !arg.contains(special) {
+        return String::from(arg);
+    }
+
+    if arg.contains('\'') {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("'{}'", arg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build(args: &str) -> Result<Options, String> {
+        Options::build(args.split(' ').map(String::from))
+    }
+
+    #[test]
+    fn it_builds_options() {
+        let options = build("todo_list --file work.csv list --json").unwrap();
+        assert_eq!(options.file, "work.csv");
+        assert!(options.json);
+        assert_eq!(options.command, vec!["list"]);
+
+        let options = build("todo_list --file=work.csv do 3").unwrap();
+        assert_eq!(options.file, "work.csv");
+        assert!(!options.json);
+        assert_eq!(options.command_line(), Some(String::from("do 3")));
+
+        let options = build("todo_list --quiet --json-lines stats").unwrap();
+        assert!(options.quiet);
+        assert!(options.json_lines);
+        assert!(!options.json);
+
+        let options = build("todo_list serve --port 8081").unwrap();
+        assert_eq!(options.port, Some(8081));
+        assert_eq!(options.command, vec!["serve"]);
+
+        let options = build("todo_list -- add --file").unwrap();
+        assert_eq!(options.command, vec!["add", "--file"]);
+    }
+
+    #[test]
+    fn it_starts_the_prompt_without_command() {
+        let options = Options::build(vec![String::from("todo_list")].into_iter()).unwrap();
+        assert_eq!(options.command_line(), None);
+    }
+
+    #[test]
+    fn it_rejects_invalid_options() {
+        assert_eq!(
+            build("todo_list list --file"),
+            Err(String::from("Missing value for --file"))
+        );
+        assert_eq!(
+            build("todo_list --verbose"),
+            Err(String::from("Unknown option --verbose"))
+        );
+        assert_eq!(
+            build("todo_list serve --port=http"),
+            Err(String::from("Invalid value for --port: http"))
+        );
+    }
+
+    #[test]
+    fn it_quotes_arguments_back() {
+        let options = Options {
+            file: String::from(DEFAULT_TASK_FILE),
+            json: false,
+            quiet: false,
+            json_lines: false,
+            port: None,
+            command: ["add", "Buy milk", "#home", "Bob's \"cafe\"", "C:\\tmp"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        };
+
+        assert_eq!(
+            options.command_line(),
+            Some(String::from(
+                r#"add 'Buy milk' #home "Bob's \"cafe\"" 'C:\tmp'"#
+            ))
+        );
+    }
+}
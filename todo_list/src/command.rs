@@ -1,14 +1,74 @@
+use std::collections::BTreeSet;
+
+use crate::exchange::Format;
+use crate::query::DueFilter;
+use crate::query::ListQuery;
+use crate::query::SortKey;
+use crate::reminders::DEFAULT_SOON_DAYS;
+use crate::search::Search;
+use crate::task::Attributes;
 use crate::task::TaskId;
+use crate::task::TaskStatus;
+use crate::tokenizer;
+use crate::tokenizer::Token;
+
+/// Tasks a [Command] acts on
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Selection {
+    Ids(Vec<TaskId>),
+    /// Every completed task
+    Done,
+}
+
+impl From<TaskId> for Selection {
+    fn from(id: TaskId) -> Self {
+        Self::Ids(vec![id])
+    }
+}
 
 /// Main [Command] type for the crate.
 /// It describes the chosen action to perform.
 #[derive(PartialEq, Eq, Debug)]
 pub enum Command {
-    List,
-    Add(String),
-    Do(TaskId),
-    UnDo(TaskId),
-    Delete(TaskId),
+    List(ListQuery),
+    Add(String, Attributes),
+    Do(Selection),
+    UnDo(Selection),
+    /// Deletes the selected tasks, along with their subtasks when set
+    Delete(Selection, bool),
+    /// Replaces the text, if given, and the given attributes of a task
+    Edit(TaskId, Option<String>, Attributes),
+    /// Blocks the first task until the second one is done
+    Block(TaskId, TaskId),
+    /// Stops the task waiting on the given task, or on any task if none
+    Unblock(TaskId, Option<TaskId>),
+    /// Gives the tasks consecutive ids starting from 1, keeping their order
+    Renumber,
+    /// Shows the recorded changes of a task
+    History(TaskId),
+    /// Shows how many tasks were completed and how fast
+    Stats,
+    /// Shows the tasks matching the search, most relevant first
+    Find(Search),
+    /// Shows the tasks not done yet that are overdue or due within the given
+    /// number of days
+    Remind(u32),
+    /// Switches to the named list, creating it if needed
+    Use(String),
+    /// Shows the name of every list
+    Lists,
+    /// Moves a task to the named list
+    Move(TaskId, String),
+    /// Writes every task to the given file
+    Export(Format, String),
+    /// Adds the tasks in the given file, in the format its extension tells
+    Import(String),
+    /// Reverts the last change made to the list
+    Revert,
+    /// Applies again the last reverted change
+    Redo,
+    /// Runs the commands in the given file, going on after failures when set
+    Run(String, bool),
     Exit,
 }
 
@@ -19,6 +79,19 @@ pub enum BuildError {
     MissingArgument(String),
     UnknownCommand,
     NotUsizeTaskId,
+    InvalidRange(String),
+    /// The range has more than [MAX_RANGE_LEN] ids
+    RangeTooLarge(String),
+    InvalidAttribute(String),
+    InvalidFilter(String),
+    InvalidListName(String),
+    InvalidFormat(String),
+    InvalidRegex(String),
+    UnexpectedArgument(String),
+    /// Column of a quote that is never closed
+    UnclosedQuote(usize),
+    /// Column of a backslash with nothing after it
+    DanglingEscape(usize),
 }
 
 impl BuildError {
@@ -33,62 +106,160 @@ impl BuildError {
             },
             BuildError::UnknownCommand => String::from("Unknown command"),
             BuildError::NotUsizeTaskId => String::from("Given task id is not an usize"),
+            BuildError::InvalidRange(text) => format!("Invalid task id range: {}", text),
+            BuildError::RangeTooLarge(text) => format!(
+                "Task id range too large: {} (at most {} ids)",
+                text, MAX_RANGE_LEN
+            ),
+            BuildError::InvalidAttribute(text) => format!("Invalid attribute: {}", text),
+            BuildError::InvalidFilter(text) => format!("Invalid filter: {}", text),
+            BuildError::InvalidListName(text) => format!(
+                "Invalid list name: {} (only letters, digits, '-' and '_' are allowed)",
+                text
+            ),
+            BuildError::InvalidFormat(text) => {
+                format!("Invalid format: {} (expected json, md or todotxt)", text)
+            },
+            BuildError::InvalidRegex(text) => format!("Invalid regex: {}", text),
+            BuildError::UnexpectedArgument(text) => format!("Unexpected argument: {}", text),
+            BuildError::UnclosedQuote(column) => {
+                format!("Unclosed quote at column {}", column)
+            },
+            BuildError::DanglingEscape(column) => {
+                format!("Nothing to escape after '\\' at column {}", column)
+            },
         }
     }
 }
 
-fn parse_text_arg(input: Vec<String>) -> String {
-    let mut arg = String::from("");
-    let mut delimiter: Option<char> = None;
-    let mut got_closing_delimiter = false;
+/// It splits the arguments into the text, every word before the first
+/// attribute, and the arguments following it.
+/// Quoted arguments are always part of the text.
+fn split_text(args: &[Token]) -> (String, &[Token]) {
+    let length = args
+        .iter()
+        .take_while(|a| a.quoted || Attributes::default().parse_arg(&a.value) == Ok(false))
+        .count();
+
+    let words: Vec<_> = args[..length].iter().map(|a| a.value.as_str()).collect();
+    (words.join(" "), &args[length..])
+}
 
-    if input.len() == 1 {
-        arg += input.get(0).unwrap();
-        if (arg.starts_with('"') && arg.ends_with('"'))
-            || (arg.starts_with('\'') && arg.ends_with('\''))
+/// It reads every argument as an attribute
+fn parse_attributes(args: &[Token]) -> Result<Attributes, BuildError> {
+    let mut attributes = Attributes::default();
+    for arg in args {
+        if !attributes
+            .parse_arg(&arg.value)
+            .map_err(BuildError::InvalidAttribute)?
         {
-            arg = String::from(arg.get(1..arg.len() - 1).unwrap());
+            return Err(BuildError::InvalidAttribute(arg.value.clone()));
         }
-        return arg;
     }
 
-    for piece in input {
-        let piece = String::from(piece.trim());
+    Ok(attributes)
+}
 
-        if delimiter.is_none() {
-            if piece.starts_with('"') || piece.starts_with('\'') {
-                delimiter = Some(piece.chars().next().unwrap());
-                arg += piece.get(1..).unwrap();
-                continue;
-            } else {
-                return piece;
-            }
+/// Most ids a single range can select
+pub const MAX_RANGE_LEN: usize = 10_000;
+
+/// It parses task ids given as lists and ranges, like `1,3,5-8` or `1 3 5-8`.
+/// Repeated ids are only kept once.
+fn parse_task_ids(args: Vec<String>) -> Result<Vec<TaskId>, BuildError> {
+    let mut ids = vec![];
+    let mut seen = BTreeSet::new();
+
+    let parse = |id: &str| id.parse::<usize>().map_err(|_| BuildError::NotUsizeTaskId);
+
+    for piece in args.iter().flat_map(|a| a.split(',')) {
+        if piece.is_empty() {
+            continue;
         }
 
-        if !arg.is_empty() {
-            if delimiter.is_none() {
-                break;
-            }
+        let (first, last) = match piece.split_once('-') {
+            Some((first, last)) => (parse(first)?, parse(last)?),
+            None => (parse(piece)?, parse(piece)?),
+        };
 
-            arg += " ";
+        if first > last {
+            return Err(BuildError::InvalidRange(String::from(piece)));
+        }
+        if last - first >= MAX_RANGE_LEN {
+            return Err(BuildError::RangeTooLarge(String::from(piece)));
         }
 
-        if delimiter.is_some() && piece.ends_with(delimiter.unwrap()) {
-            arg += piece.get(..piece.len() - 1).unwrap();
-            got_closing_delimiter = true;
-            break;
+        for id in (first..=last).map(TaskId::new) {
+            if seen.insert(id) {
+                ids.push(id);
+            }
         }
+    }
+
+    Ok(ids)
+}
+
+/// It checks a list name can be used as a file name
+fn parse_list_name(name: Option<String>, usage: &str) -> Result<String, BuildError> {
+    let name = name.ok_or(BuildError::MissingArgument(String::from(usage)))?;
 
-        arg += piece.as_str();
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(BuildError::InvalidListName(name));
     }
 
-    if !got_closing_delimiter {
-        return String::from("");
+    Ok(name)
+}
+
+/// It builds the [ListQuery] of a `list` command out of filters like `pending`,
+/// `done`, `#tag`, `due<DATE`, `search:TEXT` and `sort:KEY`
+fn parse_list_query(args: Vec<String>) -> Result<ListQuery, BuildError> {
+    let mut query = ListQuery::default();
+
+    for arg in args {
+        let invalid = || BuildError::InvalidFilter(arg.clone());
+
+        match arg.to_lowercase().as_str() {
+            "pending" => query.status = Some(TaskStatus::Pending),
+            "done" => query.status = Some(TaskStatus::Done),
+            "blocked" => query.status = Some(TaskStatus::Blocked),
+            "all" => query.all_lists = true,
+            lower if lower.starts_with("search:") => {
+                let search = &arg["search:".len()..];
+                if search.is_empty() {
+                    return Err(invalid());
+                }
+
+                query.search = Some(String::from(search));
+            },
+            lower if lower.starts_with("sort:") => {
+                query.sort = SortKey::parse(&lower["sort:".len()..]).ok_or_else(invalid)?;
+            },
+            lower if lower.starts_with("due") => {
+                query
+                    .due
+                    .push(DueFilter::parse(&lower["due".len()..]).ok_or_else(invalid)?);
+            },
+            _ => match arg.strip_prefix('#') {
+                Some(tag) if !tag.is_empty() => {
+                    query.tags.insert(String::from(tag));
+                },
+                _ => return Err(invalid()),
+            },
+        }
     }
 
-    arg
+    Ok(query)
 }
 
+/// Name of every command [build_command] knows
+pub const COMMAND_NAMES: [&str; 23] = [
+    "list", "add", "edit", "do", "undo", "delete", "block", "unblock", "renumber", "history",
+    "stats", "find", "remind", "use", "lists", "move", "export", "import", "revert", "redo", "run",
+    "source", "exit",
+];
+
 /// It builds a [Command] give the user input
 ///
 /// # Example
@@ -103,42 +274,172 @@ fn parse_text_arg(input: Vec<String>) -> String {
 /// };
 /// ```
 pub fn build_command(input: &str) -> Result<Command, BuildError> {
-    let input = input.trim();
-    let mut input = input.split_whitespace().map(|i| i.to_owned());
+    let tokens = tokenizer::tokenize(input)?;
 
-    let command_name = match input.next() {
-        Some(name) => name.to_lowercase(),
+    let (command_name, args) = match tokens.split_first() {
+        Some((name, args)) => (name.value.to_lowercase(), args),
         None => return Err(BuildError::MissingCommandName),
     };
+    let mut input = args.iter().map(|a| a.value.clone());
 
     match command_name.as_str() {
-        "list" => Ok(Command::List),
+        "list" => Ok(Command::List(parse_list_query(input.collect())?)),
         "add" => {
-            let args: Vec<_> = input.collect();
-            let parsed_arg = parse_text_arg(args);
+            let (text, rest) = split_text(args);
+
+            if text.is_empty() {
+                return Err(BuildError::MissingArgument(String::from("add 'tast text'")));
+            }
+
+            Ok(Command::Add(text, parse_attributes(rest)?))
+        },
+        "edit" => {
+            let missing = || BuildError::MissingArgument(String::from("edit TASK_ID 'new text'"));
+
+            let id = input.next().ok_or_else(missing)?;
+            let id = TaskId::new(id.parse().map_err(|_| BuildError::NotUsizeTaskId)?);
 
-            if parsed_arg.is_empty() {
-                Err(BuildError::MissingArgument(String::from("add 'tast text'")))
+            let (text, rest) = split_text(&args[1..]);
+            let text = Some(text).filter(|t| !t.is_empty());
+            let attributes = parse_attributes(rest)?;
+
+            if text.is_none() && attributes.is_empty() {
+                return Err(missing());
+            }
+
+            Ok(Command::Edit(id, text, attributes))
+        },
+        name @ ("do" | "undo" | "delete") => {
+            let mut args: Vec<_> = input.collect();
+            let cascade = name == "delete" && args.last().is_some_and(|a| a == "cascade");
+            if cascade {
+                args.pop();
+            }
+
+            let selection = if name == "delete" && args == ["done"] {
+                Selection::Done
             } else {
-                Ok(Command::Add(parsed_arg))
+                Selection::Ids(parse_task_ids(args)?)
+            };
+
+            if selection == Selection::Ids(vec![]) {
+                return Err(BuildError::MissingArgument(format!("{} TASK_ID", name)));
             }
+
+            let command = match name {
+                "do" => Command::Do(selection),
+                "undo" => Command::UnDo(selection),
+                "delete" => Command::Delete(selection, cascade),
+                _ => panic!("Should never be here"),
+            };
+            Ok(command)
         },
-        name @ ("do" | "undo" | "delete") => match input.next() {
-            Some(str) => {
-                let id = str.parse::<usize>();
-                if id.is_err() {
-                    Err(BuildError::NotUsizeTaskId)
-                } else {
-                    let command = match name {
-                        "do" => Command::Do(TaskId::new(id.ok().unwrap())),
-                        "undo" => Command::UnDo(TaskId::new(id.ok().unwrap())),
-                        "delete" => Command::Delete(TaskId::new(id.ok().unwrap())),
-                        _ => panic!("Should never be here"),
-                    };
-                    Ok(command)
-                }
-            },
-            None => Err(BuildError::MissingArgument(format!("{} TASK_ID", name))),
+        name @ ("block" | "unblock") => {
+            let (usage, keyword) = match name {
+                "block" => ("block TASK_ID on TASK_ID", "on"),
+                _ => ("unblock TASK_ID [from TASK_ID]", "from"),
+            };
+            let parse = |id: String| id.parse().map(TaskId::new);
+
+            let id = input
+                .next()
+                .ok_or(BuildError::MissingArgument(String::from(usage)))?;
+            let id = parse(id).map_err(|_| BuildError::NotUsizeTaskId)?;
+
+            let other = match input.next() {
+                Some(k) if k.to_lowercase() == keyword => Some(
+                    input
+                        .next()
+                        .ok_or(BuildError::MissingArgument(String::from(usage)))?,
+                ),
+                Some(arg) => return Err(BuildError::UnexpectedArgument(arg)),
+                None => None,
+            };
+            let other = other
+                .map(parse)
+                .transpose()
+                .map_err(|_| BuildError::NotUsizeTaskId)?;
+
+            match (name, other) {
+                ("block", Some(other)) => Ok(Command::Block(id, other)),
+                ("block", None) => Err(BuildError::MissingArgument(String::from(usage))),
+                (_, other) => Ok(Command::Unblock(id, other)),
+            }
+        },
+        "renumber" => Ok(Command::Renumber),
+        "history" => match input.next() {
+            Some(id) => Ok(Command::History(TaskId::new(
+                id.parse().map_err(|_| BuildError::NotUsizeTaskId)?,
+            ))),
+            None => Err(BuildError::MissingArgument(String::from("history TASK_ID"))),
+        },
+        "stats" => Ok(Command::Stats),
+        "find" => {
+            let query = input.collect::<Vec<_>>().join(" ");
+            if query.trim().is_empty() || query == "regex:" {
+                return Err(BuildError::MissingArgument(String::from(
+                    "find QUERY or find regex:PATTERN",
+                )));
+            }
+
+            Ok(Command::Find(
+                Search::parse(&query).map_err(BuildError::InvalidRegex)?,
+            ))
+        },
+        "remind" => match input.next() {
+            Some(days) => Ok(Command::Remind(
+                days.parse()
+                    .map_err(|_| BuildError::UnexpectedArgument(days))?,
+            )),
+            None => Ok(Command::Remind(DEFAULT_SOON_DAYS)),
+        },
+        "use" => Ok(Command::Use(parse_list_name(input.next(), "use LIST")?)),
+        "lists" => Ok(Command::Lists),
+        "move" => {
+            let usage = "move TASK_ID LIST";
+            let id = input
+                .next()
+                .ok_or(BuildError::MissingArgument(String::from(usage)))?;
+            let id = TaskId::new(id.parse().map_err(|_| BuildError::NotUsizeTaskId)?);
+
+            Ok(Command::Move(id, parse_list_name(input.next(), usage)?))
+        },
+        "export" => {
+            let usage = "export json|md|todotxt FILE";
+            let format = input
+                .next()
+                .ok_or(BuildError::MissingArgument(String::from(usage)))?;
+            let format = Format::parse(&format).ok_or(BuildError::InvalidFormat(format))?;
+
+            let file = input
+                .next()
+                .filter(|f| !f.is_empty())
+                .ok_or(BuildError::MissingArgument(String::from(usage)))?;
+
+            Ok(Command::Export(format, file))
+        },
+        "import" => {
+            let file = input
+                .next()
+                .filter(|f| !f.is_empty())
+                .ok_or(BuildError::MissingArgument(String::from("import FILE")))?;
+
+            Ok(Command::Import(file))
+        },
+        "revert" => Ok(Command::Revert),
+        "redo" => Ok(Command::Redo),
+        name @ ("run" | "source") => {
+            let usage = format!("{} FILE [continue]", name);
+            let file = input
+                .next()
+                .filter(|f| !f.is_empty())
+                .ok_or(BuildError::MissingArgument(usage))?;
+
+            match input.next().as_deref() {
+                Some("continue") => Ok(Command::Run(file, true)),
+                Some(arg) => Err(BuildError::UnexpectedArgument(String::from(arg))),
+                None => Ok(Command::Run(file, false)),
+            }
         },
         "exit" => Ok(Command::Exit),
         _ => Err(BuildError::UnknownCommand),
@@ -148,57 +449,72 @@ pub fn build_command(input: &str) -> Result<Command, BuildError> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::date::Date;
+    use crate::task::Priority;
+    use crate::task::Recurrence;
+    use crate::task::Removal;
+
+    fn split(input: &str) -> (String, Vec<String>) {
+        let tokens = tokenizer::tokenize(input).unwrap();
+        let (text, rest) = split_text(&tokens);
+        (text, rest.iter().map(|t| t.value.clone()).collect())
+    }
 
     #[test]
     fn it_parses_inputs() {
-        let output = parse_text_arg(vec![String::from("input")]);
+        let (output, _) = split("input");
         assert_eq!(output, String::from("input"));
 
-        let output = parse_text_arg(vec![String::from("'input'")]);
+        let (output, _) = split("'input'");
         assert_eq!(output, String::from("input"));
 
-        let output = parse_text_arg(
-            "input with more words that should be one"
-                .split_whitespace()
-                .map(|s| s.to_owned())
-                .collect(),
+        let (output, rest) = split("input with more words that should be one");
+        assert_eq!(
+            output,
+            String::from("input with more words that should be one")
         );
-        assert_eq!(output, String::from("input"));
+        assert!(rest.is_empty());
 
-        let output = parse_text_arg(
-            "'input with more words'"
-                .split_whitespace()
-                .map(|s| s.to_owned())
-                .collect(),
-        );
+        let (output, _) = split("'input with more words'");
         assert_eq!(output, String::from("input with more words"));
 
-        let output = parse_text_arg(
-            "\"input with more words double quotes\""
-                .split_whitespace()
-                .map(|s| s.to_owned())
-                .collect(),
-        );
+        let (output, _) = split("\"input with more words double quotes\"");
         assert_eq!(output, String::from("input with more words double quotes"));
 
-        let output = parse_text_arg(
-            "\"not closed "
-                .split_whitespace()
-                .map(|s| s.to_owned())
-                .collect(),
+        let (output, rest) =
+            split("'input with more words after the quotes' and more #tag due:2026-11-01");
+        assert_eq!(
+            output,
+            String::from("input with more words after the quotes and more")
         );
-        assert_eq!(output, String::from(""));
-
-        let output = parse_text_arg(
-            "'input with more words after the quotes' these are more words 'other text'"
-                .split_whitespace()
-                .map(|s| s.to_owned())
-                .collect(),
+        assert_eq!(
+            rest,
+            vec![String::from("#tag"), String::from("due:2026-11-01")]
         );
+
+        let (output, rest) = split("'#not-a-tag' \\#neither #tag");
+        assert_eq!(output, String::from("#not-a-tag #neither"));
+        assert_eq!(rest, vec![String::from("#tag")]);
+    }
+
+    #[test]
+    fn should_error_on_unclosed_quotes() {
+        let result = build_command("add \"not closed ");
+        assert_eq!(result, Err(BuildError::UnclosedQuote(5)));
         assert_eq!(
-            output,
-            String::from("input with more words after the quotes")
+            result.unwrap_err().val(),
+            String::from("Unclosed quote at column 5")
         );
+
+        let result = build_command("add milk \\");
+        assert_eq!(result, Err(BuildError::DanglingEscape(10)));
+    }
+
+    #[test]
+    fn should_know_every_command_name() {
+        for name in COMMAND_NAMES {
+            assert_ne!(build_command(name), Err(BuildError::UnknownCommand));
+        }
     }
 
     #[test]
@@ -210,10 +526,51 @@ mod test {
     #[test]
     fn should_create_list_command() {
         let result = build_command("list  ");
-        assert_eq!(result, Ok(Command::List));
+        assert_eq!(result, Ok(Command::List(ListQuery::default())));
 
         let result = build_command("this is not a list command");
-        assert_ne!(result, Ok(Command::List));
+        assert_ne!(result, Ok(Command::List(ListQuery::default())));
+    }
+
+    #[test]
+    fn should_create_list_command_with_query() {
+        let result = build_command("list pending #work due<2026-11-01 sort:priority");
+        let query = ListQuery {
+            status: Some(TaskStatus::Pending),
+            tags: [String::from("work")].into(),
+            due: vec![DueFilter::Before(Date::new(2026, 11, 1).unwrap())],
+            search: None,
+            sort: SortKey::Priority,
+            all_lists: false,
+        };
+        assert_eq!(result, Ok(Command::List(query)));
+
+        let result = build_command("list search:\"buy milk\" done");
+        let query = ListQuery {
+            status: Some(TaskStatus::Done),
+            search: Some(String::from("buy milk")),
+            ..ListQuery::default()
+        };
+        assert_eq!(result, Ok(Command::List(query)));
+
+        let result = build_command("list search:milk");
+        let query = ListQuery {
+            search: Some(String::from("milk")),
+            ..ListQuery::default()
+        };
+        assert_eq!(result, Ok(Command::List(query)));
+
+        let result = build_command("list sort:color");
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidFilter(String::from("sort:color")))
+        );
+
+        let result = build_command("list due<tomorrow");
+        assert!(matches!(result, Err(BuildError::InvalidFilter(_))));
+
+        let result = build_command("list everything");
+        assert!(matches!(result, Err(BuildError::InvalidFilter(_))));
     }
 
     #[test]
@@ -225,13 +582,74 @@ mod test {
         assert!(result.err().unwrap().val().starts_with("Missing argument: add"));
 
         let result = build_command(format!(" add '{task_text}'").as_str());
-        assert_eq!(result, Ok(Command::Add(String::from(task_text))));
+        assert_eq!(
+            result,
+            Ok(Command::Add(String::from(task_text), Attributes::default()))
+        );
 
         let result = build_command(format!(" add \"{task_text}\"").as_str());
-        assert_eq!(result, Ok(Command::Add(String::from(task_text))));
+        assert_eq!(
+            result,
+            Ok(Command::Add(String::from(task_text), Attributes::default()))
+        );
+
+        let result = build_command("add buy milk #home");
+        assert_eq!(
+            result,
+            Ok(Command::Add(
+                String::from("buy milk"),
+                Attributes {
+                    tags: [String::from("home")].into(),
+                    ..Attributes::default()
+                }
+            ))
+        );
+
+        let result = build_command("add buy #home milk");
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidAttribute(String::from("milk")))
+        );
 
         let result = build_command("this is not an add command");
-        assert_ne!(result, Ok(Command::Add(String::from(task_text))));
+        assert_ne!(
+            result,
+            Ok(Command::Add(String::from(task_text), Attributes::default()))
+        );
+    }
+
+    #[test]
+    fn should_create_add_command_with_attributes() {
+        let result = build_command(
+            "add 'Ship release' due:2026-11-01 prio:high #release #v2 parent:3 every:monthly",
+        );
+        let attributes = Attributes {
+            due: Date::new(2026, 11, 1),
+            priority: Some(Priority::High),
+            tags: ["release", "v2"].into_iter().map(String::from).collect(),
+            parent: Some(TaskId::new(3)),
+            recurrence: Some(Recurrence::Monthly),
+            removed: Default::default(),
+        };
+        assert_eq!(
+            result,
+            Ok(Command::Add(String::from("Ship release"), attributes))
+        );
+
+        let result = build_command("add 'Ship release' due:2026-13-01");
+        assert!(matches!(result, Err(BuildError::InvalidAttribute(_))));
+
+        let result = build_command("add 'Ship release' prio:whenever");
+        assert!(matches!(result, Err(BuildError::InvalidAttribute(_))));
+
+        let result = build_command("add 'Ship release' parent:release");
+        assert!(matches!(result, Err(BuildError::InvalidAttribute(_))));
+
+        let result = build_command("add 'Ship release' every:year");
+        assert!(matches!(result, Err(BuildError::InvalidAttribute(_))));
+
+        let result = build_command("add 'Ship release' #v2 '#two words'");
+        assert!(matches!(result, Err(BuildError::InvalidAttribute(_))));
     }
 
     #[test]
@@ -241,16 +659,16 @@ mod test {
         assert!(result.err().unwrap().val().starts_with("Missing argument: do"));
 
         let result = build_command(" do 1");
-        assert_eq!(result, Ok(Command::Do(TaskId::new(1))));
+        assert_eq!(result, Ok(Command::Do(TaskId::new(1).into())));
 
         let result = build_command(" do 1");
-        assert_eq!(result, Ok(Command::Do(TaskId::new(1))));
+        assert_eq!(result, Ok(Command::Do(TaskId::new(1).into())));
 
         let result = build_command(" do not_a_number");
         assert_eq!(result, Err(BuildError::NotUsizeTaskId));
 
         let result = build_command("this is not an do command");
-        assert_ne!(result, Ok(Command::Do(TaskId::new(1))));
+        assert_ne!(result, Ok(Command::Do(TaskId::new(1).into())));
     }
 
     #[test]
@@ -260,16 +678,16 @@ mod test {
         assert!(result.err().unwrap().val().starts_with("Missing argument: undo"));
 
         let result = build_command(" undo 1");
-        assert_eq!(result, Ok(Command::UnDo(TaskId::new(1))));
+        assert_eq!(result, Ok(Command::UnDo(TaskId::new(1).into())));
 
         let result = build_command(" undo 1");
-        assert_eq!(result, Ok(Command::UnDo(TaskId::new(1))));
+        assert_eq!(result, Ok(Command::UnDo(TaskId::new(1).into())));
 
         let result = build_command(" undo not_a_number");
         assert_eq!(result, Err(BuildError::NotUsizeTaskId));
 
         let result = build_command("this is not an undo command");
-        assert_ne!(result, Ok(Command::UnDo(TaskId::new(1))));
+        assert_ne!(result, Ok(Command::UnDo(TaskId::new(1).into())));
     }
 
     #[test]
@@ -283,16 +701,268 @@ mod test {
             .starts_with("Missing argument: delete"));
 
         let result = build_command(" delete 1");
-        assert_eq!(result, Ok(Command::Delete(TaskId::new(1))));
+        assert_eq!(result, Ok(Command::Delete(TaskId::new(1).into(), false)));
 
         let result = build_command(" delete 1");
-        assert_eq!(result, Ok(Command::Delete(TaskId::new(1))));
+        assert_eq!(result, Ok(Command::Delete(TaskId::new(1).into(), false)));
 
         let result = build_command(" delete not_a_number");
         assert_eq!(result, Err(BuildError::NotUsizeTaskId));
 
         let result = build_command("this is not a delete command");
-        assert_ne!(result, Ok(Command::Delete(TaskId::new(1))));
+        assert_ne!(result, Ok(Command::Delete(TaskId::new(1).into(), false)));
+    }
+
+    #[test]
+    fn should_create_renumber_command() {
+        let result = build_command("renumber");
+        assert_eq!(result, Ok(Command::Renumber));
+    }
+
+    #[test]
+    fn should_create_history_and_stats_commands() {
+        let result = build_command("history 3");
+        assert_eq!(result, Ok(Command::History(TaskId::new(3))));
+
+        let result = build_command("history");
+        assert!(matches!(result, Err(BuildError::MissingArgument(_))));
+
+        let result = build_command("history three");
+        assert_eq!(result, Err(BuildError::NotUsizeTaskId));
+
+        let result = build_command("stats");
+        assert_eq!(result, Ok(Command::Stats));
+    }
+
+    #[test]
+    fn should_create_find_commands() {
+        let result = build_command("find write rep #work");
+        assert_eq!(
+            result,
+            Ok(Command::Find(Search::Fuzzy(vec![
+                String::from("write"),
+                String::from("rep"),
+                String::from("#work")
+            ])))
+        );
+
+        let result = build_command("find 'regex:^buy (milk|bread)'");
+        assert_eq!(
+            result,
+            Ok(Command::Find(
+                Search::parse("regex:^buy (milk|bread)").unwrap()
+            ))
+        );
+
+        let result = build_command("find regex:[a-");
+        assert_eq!(result, Err(BuildError::InvalidRegex(String::from("[a-"))));
+
+        let result = build_command("find");
+        assert!(matches!(result, Err(BuildError::MissingArgument(_))));
+    }
+
+    #[test]
+    fn should_create_block_commands() {
+        let result = build_command("block 7 on 3");
+        assert_eq!(result, Ok(Command::Block(TaskId::new(7), TaskId::new(3))));
+
+        let result = build_command("unblock 7 from 3");
+        assert_eq!(
+            result,
+            Ok(Command::Unblock(TaskId::new(7), Some(TaskId::new(3))))
+        );
+
+        let result = build_command("unblock 7");
+        assert_eq!(result, Ok(Command::Unblock(TaskId::new(7), None)));
+
+        let result = build_command("block 7 3");
+        assert_eq!(
+            result,
+            Err(BuildError::UnexpectedArgument(String::from("3")))
+        );
+
+        let result = build_command("block 7 on");
+        assert!(matches!(result, Err(BuildError::MissingArgument(_))));
+
+        let result = build_command("block 7 on three");
+        assert_eq!(result, Err(BuildError::NotUsizeTaskId));
+    }
+
+    #[test]
+    fn should_create_remind_commands() {
+        let result = build_command("remind");
+        assert_eq!(result, Ok(Command::Remind(DEFAULT_SOON_DAYS)));
+
+        let result = build_command("remind 7");
+        assert_eq!(result, Ok(Command::Remind(7)));
+
+        let result = build_command("remind soon");
+        assert_eq!(
+            result,
+            Err(BuildError::UnexpectedArgument(String::from("soon")))
+        );
+    }
+
+    #[test]
+    fn should_create_run_commands() {
+        let result = build_command("run onboarding.todo");
+        assert_eq!(
+            result,
+            Ok(Command::Run(String::from("onboarding.todo"), false))
+        );
+
+        let result = build_command("source 'my script.todo' continue");
+        assert_eq!(
+            result,
+            Ok(Command::Run(String::from("my script.todo"), true))
+        );
+
+        let result = build_command("run script.todo later");
+        assert_eq!(
+            result,
+            Err(BuildError::UnexpectedArgument(String::from("later")))
+        );
+
+        let result = build_command("source");
+        assert!(matches!(result, Err(BuildError::MissingArgument(_))));
+    }
+
+    #[test]
+    fn should_create_list_management_commands() {
+        let result = build_command("use work");
+        assert_eq!(result, Ok(Command::Use(String::from("work"))));
+
+        let result = build_command("use ../work");
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidListName(String::from("../work")))
+        );
+
+        let result = build_command("lists");
+        assert_eq!(result, Ok(Command::Lists));
+
+        let result = build_command("move 3 personal");
+        assert_eq!(
+            result,
+            Ok(Command::Move(TaskId::new(3), String::from("personal")))
+        );
+
+        let result = build_command("move 3");
+        assert!(matches!(result, Err(BuildError::MissingArgument(_))));
+
+        let result = build_command("list all pending");
+        let query = ListQuery {
+            status: Some(TaskStatus::Pending),
+            all_lists: true,
+            ..ListQuery::default()
+        };
+        assert_eq!(result, Ok(Command::List(query)));
+    }
+
+    #[test]
+    fn should_create_export_and_import_commands() {
+        let result = build_command("export md \"my tasks.md\"");
+        assert_eq!(
+            result,
+            Ok(Command::Export(Format::Markdown, String::from("my tasks.md")))
+        );
+
+        let result = build_command("export csv tasks.csv");
+        assert_eq!(result, Err(BuildError::InvalidFormat(String::from("csv"))));
+
+        let result = build_command("export json");
+        assert!(matches!(result, Err(BuildError::MissingArgument(_))));
+
+        let result = build_command("import todo.txt");
+        assert_eq!(result, Ok(Command::Import(String::from("todo.txt"))));
+
+        let result = build_command("import");
+        assert!(matches!(result, Err(BuildError::MissingArgument(_))));
+    }
+
+    #[test]
+    fn should_create_revert_and_redo_commands() {
+        let result = build_command("revert");
+        assert_eq!(result, Ok(Command::Revert));
+
+        let result = build_command(" REDO ");
+        assert_eq!(result, Ok(Command::Redo));
+
+        let result = build_command("this is not a redo command");
+        assert_ne!(result, Ok(Command::Redo));
+    }
+
+    #[test]
+    fn should_create_edit_command() {
+        let result = build_command("edit 4");
+        assert!(result.is_err());
+        assert!(result.err().unwrap().val().starts_with("Missing argument: edit"));
+
+        let result = build_command("edit four 'New text'");
+        assert_eq!(result, Err(BuildError::NotUsizeTaskId));
+
+        let result = build_command("edit 4 'New text' prio:high");
+        let mut attributes = Attributes::default();
+        attributes.parse_arg("prio:high").unwrap();
+        assert_eq!(
+            result,
+            Ok(Command::Edit(
+                TaskId::new(4),
+                Some(String::from("New text")),
+                attributes.clone()
+            ))
+        );
+
+        let result = build_command("edit 4 prio:high");
+        assert_eq!(result, Ok(Command::Edit(TaskId::new(4), None, attributes)));
+
+        let result = build_command("edit 4 prio:high text");
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidAttribute(String::from("text")))
+        );
+        let result = build_command("edit 4 -#home due:none prio:none due:2026-11-01");
+        let attributes = Attributes {
+            due: Date::new(2026, 11, 1),
+            removed: [Removal::Tag(String::from("home")), Removal::Priority].into(),
+            ..Attributes::default()
+        };
+        assert_eq!(result, Ok(Command::Edit(TaskId::new(4), None, attributes)));
+    }
+
+    #[test]
+    fn should_create_commands_for_several_tasks() {
+        let ids = |ids: &[usize]| Selection::Ids(ids.iter().map(|i| TaskId::new(*i)).collect());
+
+        let result = build_command("do 1,3,5-8");
+        assert_eq!(result, Ok(Command::Do(ids(&[1, 3, 5, 6, 7, 8]))));
+
+        let result = build_command("undo 2 4-5,4");
+        assert_eq!(result, Ok(Command::UnDo(ids(&[2, 4, 5]))));
+
+        let result = build_command("delete done");
+        assert_eq!(result, Ok(Command::Delete(Selection::Done, false)));
+
+        let result = build_command("delete 5 cascade");
+        assert_eq!(result, Ok(Command::Delete(ids(&[5]), true)));
+
+        let result = build_command("do 5 cascade");
+        assert_eq!(result, Err(BuildError::NotUsizeTaskId));
+
+        let result = build_command("do done");
+        assert_eq!(result, Err(BuildError::NotUsizeTaskId));
+
+        let result = build_command("delete 8-5");
+        assert_eq!(result, Err(BuildError::InvalidRange(String::from("8-5"))));
+
+        let result = build_command("do 1-200000");
+        assert_eq!(
+            result,
+            Err(BuildError::RangeTooLarge(String::from("1-200000")))
+        );
+
+        let result = build_command("delete 1,x");
+        assert_eq!(result, Err(BuildError::NotUsizeTaskId));
     }
 
     #[test]
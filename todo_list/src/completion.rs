@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+
+use rustyline::completion;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::Context;
+use rustyline::Helper;
+
+use crate::command::COMMAND_NAMES;
+use crate::task::Task;
+
+/// Commands whose arguments are task ids
+const ID_COMMANDS: [&str; 8] = [
+    "do", "undo", "delete", "edit", "block", "unblock", "history", "move",
+];
+
+/// Tab completion of the interactive prompt.
+/// The first word completes to a command name, a word starting with `#` to a
+/// tag and any other argument of a command taking ids to a task id.
+#[derive(Debug, Default)]
+pub struct Completer {
+    ids: Vec<String>,
+    tags: Vec<String>,
+}
+
+impl Completer {
+    /// Takes the ids and tags to complete from the given tasks
+    pub fn update<'a>(&mut self, tasks: impl Iterator<Item = &'a Task>) {
+        let mut ids = BTreeSet::new();
+        let mut tags = BTreeSet::new();
+        for task in tasks {
+            ids.insert(*task.id());
+            tags.extend(task.tags().iter().map(|t| format!("#{}", t)));
+        }
+
+        self.ids = ids.into_iter().map(|id| id.to_string()).collect();
+        self.tags = tags.into_iter().collect();
+    }
+
+    /// Start of the word before `pos` and the candidates to replace it with
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        // Ids can also be given as a comma separated list
+        let start = before
+            .rfind(|c: char| c.is_whitespace() || c == ',')
+            .map_or(0, |i| i + 1);
+        let word = &before[start..];
+
+        let command = before.split_whitespace().next().unwrap_or("");
+        let options: Vec<&str> = if before[..start].trim().is_empty() {
+            COMMAND_NAMES.to_vec()
+        } else if word.starts_with('#') {
+            self.tags.iter().map(String::as_str).collect()
+        } else if ID_COMMANDS.contains(&command.to_lowercase().as_str()) {
+            self.ids.iter().map(String::as_str).collect()
+        } else {
+            vec![]
+        };
+
+        let candidates = options
+            .into_iter()
+            .filter(|o| o.starts_with(word))
+            .map(String::from)
+            .collect();
+
+        (start, candidates)
+    }
+}
+
+impl completion::Completer for Completer {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for Completer {
+    type Hint = String;
+}
+
+impl Highlighter for Completer {}
+
+impl Validator for Completer {}
+
+impl Helper for Completer {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::Attributes;
+    use crate::task::TaskId;
+
+    fn completer() -> Completer {
+        let mut tasks = vec![];
+        for (id, tag) in [(1, "home"), (2, "work"), (12, "homework")] {
+            let mut task = Task::new(TaskId::new(id), "Task");
+            let mut attributes = Attributes::default();
+            attributes.parse_arg(&format!("#{}", tag)).unwrap();
+            task.set_attributes(&attributes);
+            tasks.push(task);
+        }
+
+        let mut completer = Completer::default();
+        completer.update(tasks.iter());
+        completer
+    }
+
+    #[test]
+    fn it_completes_command_names() {
+        let completer = completer();
+        assert_eq!(
+            completer.candidates("  un", 4),
+            (2, vec![String::from("undo"), String::from("unblock")])
+        );
+        assert_eq!(
+            completer.candidates("re", 2),
+            (
+                0,
+                vec![
+                    String::from("renumber"),
+                    String::from("remind"),
+                    String::from("revert"),
+                    String::from("redo")
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn it_completes_task_ids_and_tags() {
+        let completer = completer();
+        assert_eq!(
+            completer.candidates("do 3,1", 6),
+            (5, vec![String::from("1"), String::from("12")])
+        );
+        assert_eq!(
+            completer.candidates("list #ho", 8),
+            (5, vec![String::from("#home"), String::from("#homework")])
+        );
+        assert_eq!(
+            completer.candidates("add 1", 5),
+            (4, vec![])
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Minimal [RFC 4180](https://www.rfc-editor.org/rfc/rfc4180) reader and writer
+//! using `;` as the field separator.
+//!
+//! Fields are only quoted when they contain a separator, a quote or a line break,
+//! so records without any of them look exactly like the original unquoted format.
+
+pub const SEPARATOR: char = ';';
+const QUOTE: char = '"';
+
+/// A single record read from CSV content
+#[derive(PartialEq, Eq, Debug)]
+pub struct Record {
+    /// The record as it appears in the content, without the line terminator
+    pub raw: String,
+    pub fields: Vec<String>,
+    /// `false` if a quoted field was never closed, in which case it was read as
+    /// plain text up to the end of its line
+    pub complete: bool,
+}
+
+fn needs_quotes(field: &str) -> bool {
+    field.contains([SEPARATOR, QUOTE, '\n', '\r'])
+}
+
+pub fn encode_field(field: &str) -> String {
+    if !needs_quotes(field) {
+        return String::from(field);
+    }
+
+    let mut encoded = String::from(QUOTE);
+    encoded += field.replace(QUOTE, "\"\"").as_str();
+    encoded.push(QUOTE);
+
+    encoded
+}
+
+pub fn encode_record(fields: &[&str]) -> String {
+    fields
+        .iter()
+        .map(|f| encode_field(f))
+        .collect::<Vec<_>>()
+        .join(SEPARATOR.to_string().as_str())
+}
+
+/// Reads every non blank record of the given content.
+///
+/// Quotes are only meaningful at the start of a field. A quoted field followed by
+/// anything other than a separator or a line break, or never closed, is read as
+/// plain text, which keeps loading files written before fields were quoted.
+pub fn parse(content: &str) -> Vec<Record> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut records = vec![];
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let start = pos;
+        let mut fields = vec![];
+        let mut complete = true;
+
+        loop {
+            let (field, next, closed) = read_field(&chars, pos);
+            fields.push(field);
+            pos = next;
+            complete = complete && closed;
+
+            match chars.get(pos) {
+                Some(&SEPARATOR) => pos += 1,
+                _ => break,
+            }
+        }
+
+        let end = pos;
+        if chars.get(pos) == Some(&'\r') {
+            pos += 1;
+        }
+        if chars.get(pos) == Some(&'\n') {
+            pos += 1;
+        }
+
+        let raw: String = chars[start..end].iter().collect();
+        let raw = String::from(raw.trim_end_matches('\r'));
+        if raw.is_empty() {
+            continue;
+        }
+
+        records.push(Record {
+            raw,
+            fields,
+            complete,
+        });
+    }
+
+    records
+}
+
+fn is_field_end(chars: &[char], pos: usize) -> bool {
+    match chars.get(pos) {
+        None | Some(&SEPARATOR) | Some(&'\n') => true,
+        Some(&'\r') => matches!(chars.get(pos + 1), None | Some(&'\n')),
+        _ => false,
+    }
+}
+
+/// Returns the field value, the position right after it and whether any quoted
+/// section was properly closed
+fn read_field(chars: &[char], start: usize) -> (String, usize, bool) {
+    let mut closed = true;
+    if chars.get(start) == Some(&QUOTE) {
+        let mut value = String::new();
+        let mut pos = start + 1;
+
+        while pos < chars.len() {
+            if chars[pos] != QUOTE {
+                value.push(chars[pos]);
+                pos += 1;
+                continue;
+            }
+
+            if chars.get(pos + 1) == Some(&QUOTE) {
+                value.push(QUOTE);
+                pos += 2;
+                continue;
+            }
+
+            if is_field_end(chars, pos + 1) {
+                return (value, pos + 1, true);
+            }
+
+            break;
+        }
+
+        // Not to lose the records after it, one never closed only goes up to
+        // the end of its line
+        closed = pos < chars.len();
+    }
+
+    let mut pos = start;
+    while !is_field_end(chars, pos) {
+        pos += 1;
+    }
+
+    (chars[start..pos].iter().collect(), pos, closed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fields(content: &str) -> Vec<Vec<String>> {
+        parse(content).into_iter().map(|r| r.fields).collect()
+    }
+
+    #[test]
+    fn it_round_trips_any_text() {
+        let texts = [
+            "plain text",
+            "with;separators;",
+            "with \"quotes\"",
+            "\"starts and ends with quotes\"",
+            "multi\nline\r\ntext",
+            "ünïcödé ✓",
+            "",
+        ];
+
+        for text in texts {
+            let content = format!("{}\n", encode_record(&["1", "pending", text]));
+            assert_eq!(
+                fields(&content),
+                vec![vec!["1", "pending", text]],
+                "text: {:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn it_reads_unquoted_records() {
+        assert_eq!(
+            fields("1;pending;Buy milk\n2;done;Say \"hi\"\n\n3;pending;\"Quoted\" word"),
+            vec![
+                vec!["1", "pending", "Buy milk"],
+                vec!["2", "done", "Say \"hi\""],
+                vec!["3", "pending", "\"Quoted\" word"],
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reads_crlf_line_endings() {
+        assert_eq!(
+            fields("1;pending;a\r\n2;done;\"b\"\r\n"),
+            vec![vec!["1", "pending", "a"], vec!["2", "done", "b"]]
+        );
+    }
+
+    #[test]
+    fn it_flags_unterminated_quotes() {
+        let records = parse("1;pending;\"never closed\n2;done;b\n");
+        assert_eq!(records.len(), 2);
+        assert!(!records[0].complete);
+        assert_eq!(records[0].fields, vec!["1", "pending", "\"never closed"]);
+        assert_eq!(records[0].raw, "1;pending;\"never closed");
+        assert!(records[1].complete);
+        assert_eq!(records[1].fields, vec!["2", "done", "b"]);
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use serde::Serializer;
+
+/// Calendar date in the `YYYY-MM-DD` format
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        Some(Self { year, month, day })
+    }
+
+    /// Parses a `YYYY-MM-DD` date
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use todo_list::date::Date;
+    ///
+    /// assert_eq!(Date::parse("2026-11-01"), Date::new(2026, 11, 1));
+    /// assert_eq!(Date::parse("2026-02-30"), None);
+    /// ```
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut pieces = input.split('-');
+        let year = pieces.next()?;
+        let month = pieces.next()?;
+        let day = pieces.next()?;
+
+        if pieces.next().is_some() || year.len() != 4 || month.len() != 2 || day.len() != 2 {
+            return None;
+        }
+
+        Self::new(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    /// Current date in UTC
+    pub fn today() -> Self {
+        Timestamp::now().date()
+    }
+
+    /// Day of the week, from 0 for Monday to 6 for Sunday
+    pub fn weekday(&self) -> u32 {
+        // 1970-01-01 was a Thursday
+        (self.to_days() + 3).rem_euclid(7) as u32
+    }
+
+    pub fn add_days(&self, days: i64) -> Self {
+        Self::from_days(self.to_days() + days)
+    }
+
+    /// The same day the given number of months later, or the last day of that
+    /// month if it is shorter
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use todo_list::date::Date;
+    ///
+    /// let date = Date::new(2026, 1, 31).unwrap();
+    /// assert_eq!(Some(date.add_months(1)), Date::new(2026, 2, 28));
+    /// ```
+    pub fn add_months(&self, months: u32) -> Self {
+        let months = self.month - 1 + months;
+        let year = self.year + (months / 12) as i32;
+        let month = months % 12 + 1;
+
+        Self {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+        }
+    }
+
+    /// Days since 1970-01-01
+    fn to_days(self) -> i64 {
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        } as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year: year as i32,
+            month,
+            day,
+        }
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Point in time, in seconds since 1970-01-01 UTC
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub fn new(seconds: u64) -> Self {
+        Self(seconds)
+    }
+
+    pub fn now() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        Self(seconds)
+    }
+
+    /// Parses a timestamp written as when displayed, like `2025-10-18 01:02:03`
+    pub fn parse(input: &str) -> Option<Self> {
+        let (date, time) = input.split_once(' ')?;
+        let days = u64::try_from(Date::parse(date)?.to_days()).ok()?;
+
+        let mut pieces = time.split(':');
+        let mut piece = |limit: u64| {
+            let piece = pieces.next().filter(|p| p.len() == 2)?;
+            piece.parse::<u64>().ok().filter(|v| *v < limit)
+        };
+        let seconds = piece(24)? * 3600 + piece(60)? * 60 + piece(60)?;
+        if pieces.next().is_some() {
+            return None;
+        }
+
+        Some(Self(days * 86_400 + seconds))
+    }
+
+    pub fn seconds(&self) -> u64 {
+        self.0
+    }
+
+    /// Day this timestamp falls on, in UTC
+    pub fn date(&self) -> Date {
+        Date::from_days((self.0 / 86_400) as i64)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.0 % 86_400;
+        write!(
+            f,
+            "{} {:02}:{:02}:{:02}",
+            self.date(),
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_valid_dates_only() {
+        assert_eq!(Date::parse("2024-02-29"), Date::new(2024, 2, 29));
+        assert_eq!(Date::parse("2023-02-29"), None);
+        assert_eq!(Date::parse("2026-13-01"), None);
+        assert_eq!(Date::parse("2026-1-01"), None);
+        assert_eq!(Date::parse("2026-01-01-01"), None);
+        assert_eq!(Date::parse("tomorrow"), None);
+    }
+
+    #[test]
+    fn it_moves_dates_forward() {
+        let date = Date::new(2024, 2, 28).unwrap();
+        assert_eq!(Some(date.add_days(1)), Date::new(2024, 2, 29));
+        assert_eq!(Some(date.add_days(2)), Date::new(2024, 3, 1));
+        assert_eq!(Some(date.add_days(365)), Date::new(2025, 2, 27));
+        assert_eq!(Some(date.add_months(11)), Date::new(2025, 1, 28));
+        assert_eq!(
+            Some(Date::new(2024, 1, 31).unwrap().add_months(1)),
+            Date::new(2024, 2, 29)
+        );
+
+        assert_eq!(Date::new(1970, 1, 1).unwrap().weekday(), 3);
+        assert_eq!(Date::new(2026, 10, 18).unwrap().weekday(), 6);
+        assert_eq!(Date::from_days(0), Date::new(1970, 1, 1).unwrap());
+        assert_eq!(Date::new(2000, 3, 1).unwrap().to_days(), 11_017);
+    }
+
+    #[test]
+    fn it_formats_timestamps_in_utc() {
+        let timestamp = Timestamp::new(1_760_745_600 + 3_723);
+        assert_eq!(timestamp.date(), Date::new(2025, 10, 18).unwrap());
+        assert_eq!(timestamp.to_string(), "2025-10-18 01:02:03");
+        assert_eq!(Timestamp::parse("2025-10-18 01:02:03"), Some(timestamp));
+        assert_eq!(Timestamp::parse("2025-10-18 24:00:00"), None);
+        assert_eq!(Timestamp::parse("2025-10-18"), None);
+    }
+
+    #[test]
+    fn it_formats_and_orders_dates() {
+        let date = Date::new(2026, 3, 7).unwrap();
+        assert_eq!(date.to_string(), "2026-03-07");
+        assert!(date < Date::new(2026, 11, 1).unwrap());
+    }
+}
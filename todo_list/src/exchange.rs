@@ -0,0 +1,575 @@
+//! Conversion of tasks from and to the formats of other tools: JSON,
+//! Markdown checklists and [todo.txt](https://github.com/todotxt/todo.txt).
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::date::Date;
+use crate::date::Timestamp;
+use crate::store::Snapshot;
+use crate::task::Attributes;
+use crate::task::Priority;
+use crate::task::Recurrence;
+use crate::task::Task;
+use crate::task::TaskId;
+use crate::task::TaskStatus;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Format {
+    /// The same array of tasks `list` prints with `--json`
+    Json,
+    /// A `- [ ] text` / `- [x] text` checklist, subtasks indented under their
+    /// parent
+    Markdown,
+    TodoTxt,
+}
+
+impl Format {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "md" | "markdown" => Some(Self::Markdown),
+            "todotxt" | "todo.txt" => Some(Self::TodoTxt),
+            _ => None,
+        }
+    }
+
+    /// Format of a file according to its extension, todo.txt unless it is
+    /// `.json` or `.md`
+    pub fn from_file(file: &str) -> Self {
+        let extension = Path::new(file)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("");
+
+        match extension.to_lowercase().as_str() {
+            "json" => Self::Json,
+            "md" | "markdown" => Self::Markdown,
+            _ => Self::TodoTxt,
+        }
+    }
+}
+
+/// Writes the given tasks, which must list subtasks right after their parent
+pub fn export(format: Format, tasks: &[&Task]) -> String {
+    match format {
+        Format::Json => serde_json::to_string_pretty(tasks).unwrap() + "\n",
+        Format::Markdown => export_markdown(tasks),
+        Format::TodoTxt => tasks.iter().map(|t| to_todo_txt(t) + "\n").collect(),
+    }
+}
+
+/// Reads the tasks in the given content.
+/// Tasks keep the id they had in JSON content, the ones in other formats are
+/// numbered in order from 1.
+/// Lines or entries that are not valid tasks are reported as warnings.
+pub fn import(format: Format, content: &str) -> Snapshot {
+    match format {
+        Format::Json => import_json(content),
+        Format::Markdown => import_markdown(content),
+        Format::TodoTxt => import_lines(content, from_todo_txt),
+    }
+}
+
+fn export_markdown(tasks: &[&Task]) -> String {
+    let mut depths = HashMap::new();
+    let mut content = String::new();
+
+    for task in tasks {
+        let depth = task
+            .parent()
+            .and_then(|p| depths.get(p))
+            .map_or(0, |d| d + 1);
+        depths.insert(*task.id(), depth);
+
+        let mark = match task.status() {
+            TaskStatus::Pending | TaskStatus::Blocked => ' ',
+            TaskStatus::Done => 'x',
+        };
+        let mut attributes = task.attributes();
+        attributes.parent = None;
+
+        content += format!("{}- [{}] {}", "  ".repeat(depth), mark, task.text()).as_str();
+        if !attributes.is_empty() {
+            content += format!(" {}", attributes).as_str();
+        }
+        content += "\n";
+    }
+
+    content
+}
+
+fn import_markdown(content: &str) -> Snapshot {
+    let mut snapshot = Snapshot::default();
+    // Indentation and id of the tasks the next one may be nested under
+    let mut parents: Vec<(usize, TaskId)> = vec![];
+
+    for line in content.lines() {
+        let item = line.trim_start();
+        if item.is_empty() || item.starts_with("# ") {
+            continue;
+        }
+
+        let indent = line.len() - item.len();
+        let id = TaskId::new(snapshot.tasks.len() + 1);
+        match from_markdown(item, id) {
+            Ok(mut task) => {
+                while parents.last().is_some_and(|(i, _)| *i >= indent) {
+                    parents.pop();
+                }
+                task.set_parent(parents.last().map(|(_, id)| *id));
+                parents.push((indent, id));
+                snapshot.tasks.push(task);
+            },
+            Err(reason) => {
+                let warning = format!("Ignoring task '{}' due to {}", line, reason);
+                snapshot.warnings.push(warning);
+            },
+        }
+    }
+
+    snapshot
+}
+
+fn from_markdown(item: &str, id: TaskId) -> Result<Task, String> {
+    let item = item
+        .strip_prefix("- ")
+        .or(item.strip_prefix("* "))
+        .ok_or(String::from("not being a checklist item"))?;
+
+    let (status, text) = if let Some(text) = item.strip_prefix("[ ] ") {
+        (TaskStatus::Pending, text)
+    } else if let Some(text) = item.strip_prefix("[x] ").or(item.strip_prefix("[X] ")) {
+        (TaskStatus::Done, text)
+    } else {
+        return Err(String::from("not being a checklist item"));
+    };
+
+    // Attributes are the words at the end that can be read as one
+    let mut words: Vec<_> = text.split_whitespace().collect();
+    let mut attributes = Attributes::default();
+    while words
+        .last()
+        .is_some_and(|w| !w.starts_with("parent:") && attributes.parse_arg(w) == Ok(true))
+    {
+        words.pop();
+    }
+
+    if words.is_empty() {
+        return Err(String::from("missing text"));
+    }
+
+    let mut task = Task::from_parts(id, status, &words.join(" "));
+    task.set_attributes(&attributes);
+
+    Ok(task)
+}
+
+/// Reads a task per line, skipping empty ones
+fn import_lines(content: &str, parse: fn(&str, TaskId) -> Result<Task, String>) -> Snapshot {
+    let mut snapshot = Snapshot::default();
+
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match parse(line.trim(), TaskId::new(snapshot.tasks.len() + 1)) {
+            Ok(task) => snapshot.tasks.push(task),
+            Err(reason) => {
+                let warning = format!("Ignoring task '{}' due to {}", line, reason);
+                snapshot.warnings.push(warning);
+            },
+        }
+    }
+
+    snapshot
+}
+
+/// todo.txt has letter priorities, from `(A)` as the most important one
+fn todo_txt_priority(priority: Priority) -> Option<char> {
+    match priority {
+        Priority::Urgent => Some('A'),
+        Priority::High => Some('B'),
+        Priority::Normal => None,
+        Priority::Low => Some('C'),
+    }
+}
+
+/// Letters after `C` are all low priorities
+fn priority_from_todo_txt(letter: &str) -> Option<Priority> {
+    match letter {
+        "A" => Some(Priority::Urgent),
+        "B" => Some(Priority::High),
+        l if l.len() == 1 && l.chars().all(|c| c.is_ascii_uppercase()) => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// Recurrences as todo.txt apps write them, `b` being business days
+fn todo_txt_recurrence(recurrence: Recurrence) -> &'static str {
+    match recurrence {
+        Recurrence::Daily => "1d",
+        Recurrence::Weekday => "1b",
+        Recurrence::Weekly => "1w",
+        Recurrence::Monthly => "1m",
+    }
+}
+
+fn recurrence_from_todo_txt(input: &str) -> Option<Recurrence> {
+    match input.trim_start_matches('+') {
+        "d" | "1d" => Some(Recurrence::Daily),
+        "b" | "1b" => Some(Recurrence::Weekday),
+        "w" | "1w" => Some(Recurrence::Weekly),
+        "m" | "1m" => Some(Recurrence::Monthly),
+        r => Recurrence::parse(r),
+    }
+}
+
+/// A line like `x 2026-10-18 2026-10-01 Write report +work due:2026-10-20`.
+/// Tags are written as projects and subtasks lose their parent.
+fn to_todo_txt(task: &Task) -> String {
+    let date = |t: Option<&Timestamp>| t.map(|t| t.date().to_string());
+    let priority = todo_txt_priority(*task.priority());
+    let mut pieces = vec![];
+
+    match task.status() {
+        TaskStatus::Done => {
+            pieces.push(String::from("x"));
+            // The creation date is only allowed after the completion one
+            if let Some(completed) = date(task.completed()) {
+                pieces.push(completed);
+                pieces.extend(date(task.created()));
+            }
+        },
+        TaskStatus::Pending | TaskStatus::Blocked => {
+            pieces.extend(priority.map(|p| format!("({})", p)));
+            pieces.extend(date(task.created()));
+        },
+    }
+
+    pieces.push(String::from(task.text()));
+    pieces.extend(task.tags().iter().map(|t| format!("+{}", t)));
+    pieces.extend(task.due().map(|d| format!("due:{}", d)));
+    pieces.extend(
+        task.recurrence()
+            .map(|r| format!("rec:{}", todo_txt_recurrence(*r))),
+    );
+    if task.status() == &TaskStatus::Done {
+        pieces.extend(priority.map(|p| format!("pri:{}", p)));
+    }
+
+    pieces.join(" ")
+}
+
+/// Completion and creation dates are left out, as the task is new to the list
+/// it is imported to
+fn from_todo_txt(line: &str, id: TaskId) -> Result<Task, String> {
+    let mut words = line.split_whitespace().peekable();
+    let mut status = TaskStatus::Pending;
+    let mut attributes = Attributes::default();
+
+    if words.next_if_eq(&"x").is_some() {
+        status = TaskStatus::Done;
+    }
+    if let Some(word) = words.next_if(|w| w.len() == 3 && w.starts_with('(') && w.ends_with(')')) {
+        attributes.priority = priority_from_todo_txt(&word[1..2]);
+    }
+    while words.next_if(|w| Date::parse(w).is_some()).is_some() {}
+
+    let mut text = vec![];
+    for word in words {
+        if let Some(tag) = word.strip_prefix('+').or(word.strip_prefix('@')) {
+            if !tag.is_empty() {
+                attributes.tags.insert(String::from(tag));
+                continue;
+            }
+        } else if let Some(due) = word.strip_prefix("due:") {
+            let due = Date::parse(due).ok_or(format!("invalid due date '{}'", due))?;
+            attributes.due = Some(due);
+            continue;
+        } else if let Some(recurrence) = word.strip_prefix("rec:") {
+            attributes.recurrence = Some(
+                recurrence_from_todo_txt(recurrence)
+                    .ok_or(format!("invalid recurrence '{}'", recurrence))?,
+            );
+            continue;
+        } else if let Some(priority) = word.strip_prefix("pri:") {
+            attributes.priority = priority_from_todo_txt(priority);
+            continue;
+        }
+
+        text.push(word);
+    }
+
+    if text.is_empty() {
+        return Err(String::from("missing text"));
+    }
+
+    let mut task = Task::from_parts(id, status, &text.join(" "));
+    task.set_attributes(&attributes);
+
+    Ok(task)
+}
+
+fn import_json(content: &str) -> Snapshot {
+    let mut snapshot = Snapshot::default();
+
+    let entries = match serde_json::from_str(content) {
+        Ok(Value::Array(entries)) => entries,
+        Ok(_) => {
+            let warning = String::from("Ignoring every task due to not being a JSON array");
+            snapshot.warnings.push(warning);
+            return snapshot;
+        },
+        Err(e) => {
+            let warning = format!("Ignoring every task due to invalid JSON '{}'", e);
+            snapshot.warnings.push(warning);
+            return snapshot;
+        },
+    };
+
+    for entry in entries {
+        let id = TaskId::new(snapshot.tasks.len() + 1);
+        match from_json(&entry, id) {
+            Ok(task) => snapshot.tasks.push(task),
+            Err(reason) => {
+                let warning = format!("Ignoring task '{}' due to {}", entry, reason);
+                snapshot.warnings.push(warning);
+            },
+        }
+    }
+
+    snapshot
+}
+
+/// Fields of a task given as a JSON object, any of them may be missing
+#[derive(PartialEq, Eq, Debug, Default)]
+pub struct TaskFields {
+    pub id: Option<TaskId>,
+    pub text: Option<String>,
+    pub status: Option<TaskStatus>,
+    pub attributes: Attributes,
+    pub blockers: BTreeSet<TaskId>,
+}
+
+/// Ids leave room for the one after them, which the next created task gets
+fn task_id(value: &Value) -> Option<TaskId> {
+    let id = usize::try_from(value.as_u64()?).ok()?;
+    (id <= TaskId::MAX).then(|| TaskId::new(id))
+}
+
+/// Reads the fields of a task written as in [export], missing and `null`
+/// fields being the same
+pub fn read_fields(entry: &Value) -> Result<TaskFields, String> {
+    let field = |name: &str| entry.get(name).filter(|v| !v.is_null());
+    let string = |name: &str| match field(name) {
+        Some(Value::String(s)) => Ok(Some(s.as_str())),
+        Some(_) => Err(format!("invalid {}", name)),
+        None => Ok(None),
+    };
+    let id_field = |name: &str| match field(name) {
+        Some(v) => task_id(v).map(Some).ok_or(format!("invalid {}", name)),
+        None => Ok(None),
+    };
+
+    let status = match string("status")? {
+        Some(status) => Some(TaskStatus::parse(status).ok_or(String::from("invalid status"))?),
+        None => None,
+    };
+
+    let mut attributes = Attributes {
+        parent: id_field("parent")?,
+        ..Attributes::default()
+    };
+    if let Some(due) = string("due")? {
+        attributes.due = Some(Date::parse(due).ok_or(String::from("invalid due"))?);
+    }
+    if let Some(priority) = string("priority")? {
+        attributes.priority =
+            Some(Priority::parse(priority).ok_or(String::from("invalid priority"))?);
+    }
+    if let Some(recurrence) = string("recurrence")? {
+        attributes.recurrence =
+            Some(Recurrence::parse(recurrence).ok_or(String::from("invalid recurrence"))?);
+    }
+    match field("tags") {
+        Some(Value::Array(tags)) => {
+            for tag in tags {
+                let tag = tag
+                    .as_str()
+                    .filter(|t| !t.is_empty() && !t.contains(char::is_whitespace))
+                    .ok_or(String::from("invalid tags"))?;
+                attributes.tags.insert(String::from(tag));
+            }
+        },
+        Some(_) => return Err(String::from("invalid tags")),
+        None => (),
+    }
+
+    let mut blockers = BTreeSet::new();
+    match field("blockers") {
+        Some(Value::Array(ids)) => {
+            for id in ids {
+                blockers.insert(task_id(id).ok_or(String::from("invalid blockers"))?);
+            }
+        },
+        Some(_) => return Err(String::from("invalid blockers")),
+        None => (),
+    }
+
+    Ok(TaskFields {
+        id: id_field("id")?,
+        text: string("text")?.map(String::from),
+        status,
+        attributes,
+        blockers,
+    })
+}
+
+/// Reads a task written by [export], the given id being used if it has none
+fn from_json(entry: &Value, id: TaskId) -> Result<Task, String> {
+    let fields = read_fields(entry)?;
+    let text = fields.text.ok_or(String::from("missing text"))?;
+
+    let timestamp = |name: &str| match entry.get(name).filter(|v| !v.is_null()) {
+        Some(Value::String(t)) => Timestamp::parse(t)
+            .map(Some)
+            .ok_or(format!("invalid {}", name)),
+        Some(_) => Err(format!("invalid {}", name)),
+        None => Ok(None),
+    };
+
+    let status = fields.status.unwrap_or(TaskStatus::Pending);
+    let mut task = Task::from_parts(fields.id.unwrap_or(id), status, &text);
+    task.set_attributes(&fields.attributes);
+    task.set_blockers(fields.blockers);
+    task.set_times(
+        timestamp("created")?,
+        timestamp("completed")?,
+        timestamp("modified")?,
+    );
+
+    Ok(task)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tasks() -> Vec<Task> {
+        let mut report = Task::new(TaskId::new(1), "Write report");
+        let mut attributes = Attributes::default();
+        attributes.parse_arg("due:2026-10-20").unwrap();
+        attributes.parse_arg("prio:urgent").unwrap();
+        attributes.parse_arg("#work").unwrap();
+        report.set_attributes(&attributes);
+
+        let mut draft = Task::new(TaskId::new(4), "Draft it");
+        draft.set_parent(Some(TaskId::new(1)));
+        draft.r#do();
+
+        vec![report, draft]
+    }
+
+    #[test]
+    fn it_exports_and_imports_markdown_checklists() {
+        let tasks = tasks();
+        let content = export(Format::Markdown, &tasks.iter().collect::<Vec<_>>());
+        assert_eq!(
+            content,
+            "- [ ] Write report due:2026-10-20 prio:urgent #work\n  - [x] Draft it\n"
+        );
+
+        let snapshot = import(
+            Format::Markdown,
+            &format!("# Work\n\n{}* [?] Maybe\n", content),
+        );
+        assert_eq!(snapshot.tasks[0].attributes(), tasks[0].attributes());
+        assert_eq!(snapshot.tasks[1].text(), "Draft it");
+        assert_eq!(snapshot.tasks[1].status(), &TaskStatus::Done);
+        assert_eq!(snapshot.tasks[1].parent(), Some(&TaskId::new(1)));
+        assert_eq!(
+            snapshot.warnings,
+            vec![String::from(
+                "Ignoring task '* [?] Maybe' due to not being a checklist item"
+            )]
+        );
+    }
+
+    #[test]
+    fn it_exports_and_imports_todo_txt() {
+        let tasks = tasks();
+        let content = export(Format::TodoTxt, &tasks.iter().collect::<Vec<_>>());
+        assert_eq!(
+            content,
+            "(A) Write report +work due:2026-10-20\nx Draft it\n"
+        );
+
+        let content = "x 2026-10-18 2026-10-01 Call mom @phone pri:B rec:+1w\n\
+                       (C) 2026-10-01 Water plants due:tomorrow\n\
+                       +home";
+        let snapshot = import(Format::TodoTxt, content);
+        assert_eq!(snapshot.tasks.len(), 1);
+
+        let task = &snapshot.tasks[0];
+        assert_eq!(task.text(), "Call mom");
+        assert_eq!(task.status(), &TaskStatus::Done);
+        assert_eq!(task.priority(), &Priority::High);
+        assert_eq!(task.recurrence(), Some(&Recurrence::Weekly));
+        assert!(task.tags().contains("phone"));
+        assert_eq!(
+            snapshot.warnings,
+            vec![
+                String::from(
+                    "Ignoring task '(C) 2026-10-01 Water plants due:tomorrow' due to invalid due date 'tomorrow'"
+                ),
+                String::from("Ignoring task '+home' due to missing text"),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_exports_and_imports_json() {
+        let mut tasks = tasks();
+        let (created, completed) = (Timestamp::new(1_760_745_600), Timestamp::new(1_760_832_000));
+        tasks[1].set_times(Some(created), Some(completed), Some(completed));
+        let content = export(Format::Json, &tasks.iter().collect::<Vec<_>>());
+
+        let snapshot = import(Format::Json, &content);
+        assert_eq!(snapshot.tasks, tasks);
+        assert!(snapshot.warnings.is_empty());
+
+        let snapshot = import(Format::Json, r#"[{"text": "Buy milk"}, {"id": 3}]"#);
+        assert_eq!(snapshot.tasks, vec![Task::new(TaskId::new(1), "Buy milk")]);
+        assert_eq!(
+            snapshot.warnings,
+            vec![String::from(
+                "Ignoring task '{\"id\":3}' due to missing text"
+            )]
+        );
+
+        let snapshot = import(Format::Json, r#"[{"text": "Call", "tags": ["two words"]}]"#);
+        assert!(snapshot.tasks.is_empty());
+        assert!(snapshot.warnings[0].ends_with("due to invalid tags"));
+
+        let snapshot = import(
+            Format::Json,
+            r#"[{"id": 18446744073709551615, "text": "Huge"}]"#,
+        );
+        assert!(snapshot.tasks.is_empty());
+        assert!(snapshot.warnings[0].ends_with("due to invalid id"));
+
+        let snapshot = import(Format::Json, "{");
+        assert!(snapshot.tasks.is_empty());
+        assert_eq!(snapshot.warnings.len(), 1);
+    }
+
+    #[test]
+    fn it_guesses_the_format_from_the_file_extension() {
+        assert_eq!(Format::from_file("tasks.json"), Format::Json);
+        assert_eq!(Format::from_file("notes/tasks.MD"), Format::Markdown);
+        assert_eq!(Format::from_file("todo.txt"), Format::TodoTxt);
+        assert_eq!(Format::parse("todotxt"), Some(Format::TodoTxt));
+        assert_eq!(Format::parse("csv"), None);
+    }
+}
@@ -0,0 +1,341 @@
+use std::fs;
+use std::io;
+use std::path;
+
+use crate::csv;
+use crate::date::Timestamp;
+use crate::store;
+use crate::task::Task;
+use crate::task::TaskId;
+use crate::task::TaskStatus;
+
+/// Maximum number of commands that can be reverted
+const HISTORY_LIMIT: usize = 100;
+
+/// State of a single task right before and after a command.
+/// `None` means the task did not exist.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Change {
+    pub id: TaskId,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+    /// Time the command was applied at, missing on changes recorded before
+    /// timestamps existed
+    pub at: Option<Timestamp>,
+}
+
+impl Change {
+    /// What the change did to the task, in a couple of words
+    pub fn summary(&self) -> String {
+        match (&self.before, &self.after) {
+            (None, Some(_)) => String::from("created"),
+            (Some(_), None) => String::from("deleted"),
+            (Some(before), Some(after)) if before.status() != after.status() => {
+                match (before.status(), after.status()) {
+                    (_, TaskStatus::Done) => String::from("done"),
+                    (_, TaskStatus::Blocked) => String::from("blocked"),
+                    (TaskStatus::Blocked, TaskStatus::Pending) => String::from("unblocked"),
+                    (_, TaskStatus::Pending) => String::from("reopened"),
+                }
+            },
+            _ => String::from("edited"),
+        }
+    }
+}
+
+/// Every change made by a single command
+pub type Transaction = Vec<Change>;
+
+/// Undo and redo stacks of the commands executed on a
+/// [TaskList](crate::task_list::TaskList).
+/// Only the last [HISTORY_LIMIT] commands are kept, the whole record of what
+/// happened to each task is its [Activity](crate::activity::Activity).
+#[derive(Debug, Default)]
+pub struct History {
+    file: Option<String>,
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+}
+
+impl History {
+    /// History that only lasts for the current session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// History kept in the given file so it survives across sessions
+    pub fn persistent(file: &str) -> Self {
+        Self {
+            file: Some(String::from(file)),
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the stacks with the ones in the history file, returning a
+    /// warning for every entry that could not be read.
+    /// Other sessions may write the file too, so it is read again before
+    /// every change.
+    pub fn load(&mut self) -> Result<Vec<String>, io::Error> {
+        let mut warnings = vec![];
+
+        let file = match &self.file {
+            Some(f) => f,
+            None => return Ok(warnings),
+        };
+        self.undo.clear();
+        self.redo.clear();
+        if !path::Path::new(file).exists() {
+            return Ok(warnings);
+        }
+
+        let content = fs::read_to_string(file)?;
+        let mut current: Option<(String, String)> = None;
+
+        for record in csv::parse(&content) {
+            let (stack, index, change) = match decode(&record) {
+                Ok(decoded) => decoded,
+                Err(reason) => {
+                    warnings.push(format!(
+                        "Ignoring history entry '{}' due to {}",
+                        record.raw, reason
+                    ));
+                    continue;
+                },
+            };
+
+            let stack_key = (String::from(stack), index);
+            let transactions = match stack {
+                "undo" => &mut self.undo,
+                _ => &mut self.redo,
+            };
+
+            if current.as_ref() != Some(&stack_key) {
+                transactions.push(vec![]);
+                current = Some(stack_key);
+            }
+            transactions.last_mut().unwrap().push(change);
+        }
+
+        Ok(warnings)
+    }
+
+    /// Stores the changes of a new command, dropping anything that could be
+    /// redone
+    pub fn record(&mut self, transaction: Transaction) -> Result<(), io::Error> {
+        if transaction.is_empty() {
+            return Ok(());
+        }
+
+        self.undo.push(transaction);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+
+        self.save()
+    }
+
+    /// Returns the changes of the last command so they can be rolled back
+    pub fn undo(&mut self) -> Result<Option<Transaction>, io::Error> {
+        let transaction = match self.undo.pop() {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+
+        self.redo.push(transaction.clone());
+        self.save()?;
+
+        Ok(Some(transaction))
+    }
+
+    /// Returns the changes of the last reverted command so they can be applied
+    /// again
+    pub fn redo(&mut self) -> Result<Option<Transaction>, io::Error> {
+        let transaction = match self.redo.pop() {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+
+        self.undo.push(transaction.clone());
+        self.save()?;
+
+        Ok(Some(transaction))
+    }
+
+    fn save(&self) -> Result<(), io::Error> {
+        let file = match &self.file {
+            Some(f) => f,
+            None => return Ok(()),
+        };
+
+        let mut content = String::new();
+        for (stack, transactions) in [("undo", &self.undo), ("redo", &self.redo)] {
+            for (index, transaction) in transactions.iter().enumerate() {
+                for change in transaction {
+                    content += format!("{}\n", encode(stack, index, change)).as_str();
+                }
+            }
+        }
+
+        store::write_atomically(file, content.as_bytes())
+    }
+}
+
+fn encode(stack: &str, index: usize, change: &Change) -> String {
+    let mut fields = vec![String::from(stack), index.to_string()];
+    fields.extend(encode_change(change));
+
+    let fields: Vec<_> = fields.iter().map(|f| f.as_str()).collect();
+    csv::encode_record(&fields)
+}
+
+fn decode(record: &csv::Record) -> Result<(&str, String, Change), String> {
+    let fields = &record.fields;
+    if !record.complete || fields.len() < 2 {
+        return Err(String::from("missmatched parts"));
+    }
+
+    let stack = match fields[0].as_str() {
+        "undo" => "undo",
+        "redo" => "redo",
+        _ => return Err(String::from("invalid stack")),
+    };
+
+    Ok((stack, fields[1].clone(), decode_change(&fields[2..])?))
+}
+
+/// Id, states and time of a change, as written in the history file and the
+/// [Activity](crate::activity::Activity) of the tasks
+pub(crate) fn encode_change(change: &Change) -> [String; 4] {
+    [
+        change.id.val().to_string(),
+        change.before.as_ref().map_or(String::new(), |t| t.to_csv()),
+        change.after.as_ref().map_or(String::new(), |t| t.to_csv()),
+        change.at.map_or(String::new(), |t| t.seconds().to_string()),
+    ]
+}
+
+pub(crate) fn decode_change(fields: &[String]) -> Result<Change, String> {
+    // Changes recorded before timestamps existed lack the last field
+    if fields.len() != 3 && fields.len() != 4 {
+        return Err(String::from("missmatched parts"));
+    }
+
+    let id = match fields[0].parse() {
+        Ok(id) => TaskId::new(id),
+        Err(_) => return Err(String::from("invalid id")),
+    };
+
+    Ok(Change {
+        id,
+        before: decode_task(&fields[1])?,
+        after: decode_task(&fields[2])?,
+        at: match fields.get(3).map(|f| f.as_str()) {
+            None | Some("") => None,
+            Some(at) => Some(Timestamp::new(
+                at.parse().map_err(|_| String::from("invalid timestamp"))?,
+            )),
+        },
+    })
+}
+
+fn decode_task(row: &str) -> Result<Option<Task>, String> {
+    if row.is_empty() {
+        return Ok(None);
+    }
+
+    match csv::parse(row).first() {
+        Some(record) => Task::from_csv(&record.fields).map(Some),
+        None => Err(String::from("missmatched parts")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::*;
+
+    fn change(id: usize, before: Option<&str>, after: Option<&str>) -> Change {
+        let id = TaskId::new(id);
+        Change {
+            id,
+            before: before.map(|t| Task::new(id, t)),
+            after: after.map(|t| Task::new(id, t)),
+            at: Some(Timestamp::new(1_760_000_000)),
+        }
+    }
+
+    #[test]
+    fn it_moves_transactions_between_stacks() {
+        let mut history = History::new();
+        assert_eq!(history.undo().unwrap(), None);
+
+        history.record(vec![change(1, None, Some("a"))]).unwrap();
+        history.record(vec![change(2, None, Some("b"))]).unwrap();
+
+        assert_eq!(
+            history.undo().unwrap(),
+            Some(vec![change(2, None, Some("b"))])
+        );
+        assert_eq!(
+            history.redo().unwrap(),
+            Some(vec![change(2, None, Some("b"))])
+        );
+        assert_eq!(history.redo().unwrap(), None);
+
+        history.undo().unwrap();
+        history.record(vec![change(3, None, Some("c"))]).unwrap();
+        assert_eq!(history.redo().unwrap(), None);
+    }
+
+    #[test]
+    fn it_persists_both_stacks() {
+        let file = env::temp_dir().join(format!("todo_list_{}_history", std::process::id()));
+        let file = file.to_str().unwrap();
+        let _ = fs::remove_file(file);
+
+        let mut history = History::persistent(file);
+        history
+            .record(vec![
+                change(1, Some("a;\"b\""), None),
+                change(2, Some("c"), Some("d\ne")),
+            ])
+            .unwrap();
+        history.record(vec![change(3, None, Some("f"))]).unwrap();
+        history.undo().unwrap();
+
+        let mut loaded = History::persistent(file);
+        assert!(loaded.load().unwrap().is_empty());
+        assert_eq!(loaded.undo, history.undo);
+        assert_eq!(loaded.redo, history.redo);
+        assert_eq!(loaded.undo[0][1].summary(), "edited");
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_reloads_what_other_sessions_recorded() {
+        let file = env::temp_dir().join(format!("todo_list_{}_shared_history", std::process::id()));
+        let file = file.to_str().unwrap();
+        let _ = fs::remove_file(file);
+
+        let mut first = History::persistent(file);
+        let mut second = History::persistent(file);
+        first.record(vec![change(1, None, Some("a"))]).unwrap();
+        second.load().unwrap();
+        second.record(vec![change(2, None, Some("b"))]).unwrap();
+
+        first.load().unwrap();
+        assert_eq!(
+            first.undo().unwrap(),
+            Some(vec![change(2, None, Some("b"))])
+        );
+        assert_eq!(
+            first.undo().unwrap(),
+            Some(vec![change(1, None, Some("a"))])
+        );
+
+        fs::remove_file(file).unwrap();
+    }
+}
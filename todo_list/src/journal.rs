@@ -0,0 +1,354 @@
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path;
+
+use crate::command::Command;
+use crate::command::Selection;
+use crate::csv;
+use crate::date::Timestamp;
+use crate::task::Attributes;
+use crate::task::TaskId;
+
+/// A journaled [Command].
+/// `created` holds the id assigned by an [Command::Add] so replaying it twice
+/// does not duplicate the task, and `at` the time it was first applied, unless
+/// it was journaled before timestamps existed.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub command: Command,
+    pub created: Option<TaskId>,
+    pub at: Option<Timestamp>,
+}
+
+/// Result of reading a [Journal]
+#[derive(Debug, Default)]
+pub struct Replay {
+    pub entries: Vec<Entry>,
+    pub warnings: Vec<String>,
+}
+
+/// Append-only log of the commands applied since the last saved snapshot
+#[derive(Debug)]
+pub struct Journal {
+    file: String,
+    len: usize,
+    size: u64,
+}
+
+impl Journal {
+    pub fn new(file: &str) -> Self {
+        Self {
+            file: String::from(file),
+            len: 0,
+            size: 0,
+        }
+    }
+
+    /// Number of entries written since the journal was last cleared
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether another process wrote to the journal since this one last read
+    /// or wrote it
+    pub fn is_modified(&self) -> Result<bool, io::Error> {
+        Ok(self.current_size()? != self.size)
+    }
+
+    fn current_size(&self) -> Result<u64, io::Error> {
+        match fs::metadata(&self.file) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn load(&mut self) -> Result<Replay, io::Error> {
+        let mut replay = Replay::default();
+
+        if !path::Path::new(&self.file).exists() {
+            self.len = 0;
+            self.size = 0;
+            return Ok(replay);
+        }
+
+        let content = fs::read_to_string(&self.file)?;
+        for record in csv::parse(&content) {
+            match decode(&record) {
+                Some(entry) => replay.entries.push(entry),
+                None => replay
+                    .warnings
+                    .push(format!("Ignoring journal entry '{}'", record.raw)),
+            }
+        }
+
+        self.len = replay.entries.len();
+        self.size = content.len() as u64;
+
+        Ok(replay)
+    }
+
+    /// Durably appends the command applied at the given time before returning
+    pub fn record(
+        &mut self,
+        command: &Command,
+        created: Option<TaskId>,
+        at: Timestamp,
+    ) -> Result<(), io::Error> {
+        let line = match encode(command, created, at) {
+            Some(l) => l,
+            None => return Ok(()),
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)?;
+        file.write_all(format!("{}\n", line).as_bytes())?;
+        file.sync_data()?;
+
+        self.len += 1;
+        self.size = self.current_size()?;
+
+        Ok(())
+    }
+
+    /// Drops every entry, meant to be called once they are part of a snapshot
+    pub fn clear(&mut self) -> Result<(), io::Error> {
+        let file = File::create(&self.file)?;
+        file.sync_all()?;
+
+        self.len = 0;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+fn encode(command: &Command, created: Option<TaskId>, at: Timestamp) -> Option<String> {
+    let line = match command {
+        Command::Add(text, attributes) => {
+            let mut fields = vec![
+                String::from("add"),
+                created?.val().to_string(),
+                text.clone(),
+            ];
+            fields.extend(attributes.to_fields());
+
+            let fields: Vec<_> = fields.iter().map(|f| f.as_str()).collect();
+            csv::encode_record(&fields)
+        },
+        Command::Edit(id, text, attributes) => {
+            let mut fields = vec![
+                String::from("edit"),
+                id.val().to_string(),
+                text.clone().unwrap_or_default(),
+            ];
+            fields.extend(attributes.to_fields());
+
+            let fields: Vec<_> = fields.iter().map(|f| f.as_str()).collect();
+            csv::encode_record(&fields)
+        },
+        Command::Do(selection) => csv::encode_record(&["do", &encode_ids(selection)?]),
+        Command::UnDo(selection) => csv::encode_record(&["undo", &encode_ids(selection)?]),
+        Command::Delete(selection, false) => {
+            csv::encode_record(&["delete", &encode_ids(selection)?])
+        },
+        Command::Delete(selection, true) => {
+            csv::encode_record(&["delete", &encode_ids(selection)?, "cascade"])
+        },
+        Command::Block(id, other) => {
+            csv::encode_record(&["block", &id.val().to_string(), &other.val().to_string()])
+        },
+        Command::Unblock(id, None) => csv::encode_record(&["unblock", &id.val().to_string()]),
+        Command::Unblock(id, Some(other)) => {
+            csv::encode_record(&["unblock", &id.val().to_string(), &other.val().to_string()])
+        },
+        _ => return None,
+    };
+
+    Some(format!("{}{}{}", at.seconds(), csv::SEPARATOR, line))
+}
+
+/// Only resolved ids can be journaled, anything else depends on the state the
+/// command is applied to
+fn encode_ids(selection: &Selection) -> Option<String> {
+    match selection {
+        Selection::Ids(ids) => {
+            let ids: Vec<_> = ids.iter().map(|id| id.val().to_string()).collect();
+            Some(ids.join(","))
+        },
+        Selection::Done => None,
+    }
+}
+
+fn decode_ids(field: &str) -> Option<Selection> {
+    let ids: Option<Vec<_>> = field
+        .split(',')
+        .map(|id| id.parse().ok().map(TaskId::new))
+        .collect();
+
+    ids.map(Selection::Ids)
+}
+
+/// Entries written since timestamps exist start with the time the command was
+/// applied at
+fn decode(record: &csv::Record) -> Option<Entry> {
+    if !record.complete {
+        return None;
+    }
+
+    let (at, fields) = match record.fields.first()?.parse() {
+        Ok(seconds) => (Some(Timestamp::new(seconds)), &record.fields[1..]),
+        Err(_) => (None, &record.fields[..]),
+    };
+    let ids = fields.get(1)?;
+
+    let (command, created) = match (fields[0].as_str(), fields.len()) {
+        ("add", 3) => (
+            Command::Add(fields[2].clone(), Attributes::default()),
+            Some(TaskId::new(ids.parse().ok()?)),
+        ),
+        ("add", 6..=9) => (
+            Command::Add(
+                fields[2].clone(),
+                Attributes::from_fields(&fields[3..]).ok()?,
+            ),
+            Some(TaskId::new(ids.parse().ok()?)),
+        ),
+        // Edited texts are never empty, so an empty one means it was kept
+        ("edit", 6..=9) => (
+            Command::Edit(
+                TaskId::new(ids.parse().ok()?),
+                Some(fields[2].clone()).filter(|t| !t.is_empty()),
+                Attributes::from_fields(&fields[3..]).ok()?,
+            ),
+            None,
+        ),
+        ("do", 2) => (Command::Do(decode_ids(ids)?), None),
+        ("undo", 2) => (Command::UnDo(decode_ids(ids)?), None),
+        ("delete", 2) => (Command::Delete(decode_ids(ids)?, false), None),
+        ("delete", 3) if fields[2] == "cascade" => (Command::Delete(decode_ids(ids)?, true), None),
+        ("block", 3) => (
+            Command::Block(
+                TaskId::new(ids.parse().ok()?),
+                TaskId::new(fields[2].parse().ok()?),
+            ),
+            None,
+        ),
+        ("unblock", 2) => (Command::Unblock(TaskId::new(ids.parse().ok()?), None), None),
+        ("unblock", 3) => (
+            Command::Unblock(
+                TaskId::new(ids.parse().ok()?),
+                Some(TaskId::new(fields[2].parse().ok()?)),
+            ),
+            None,
+        ),
+        _ => return None,
+    };
+
+    Some(Entry {
+        command,
+        created,
+        at,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::*;
+    use crate::query::ListQuery;
+
+    #[test]
+    fn it_records_and_replays_commands() {
+        let file = env::temp_dir().join(format!("todo_list_{}_journal", std::process::id()));
+        let file = file.to_str().unwrap();
+        let _ = fs::remove_file(file);
+
+        let mut journal = Journal::new(file);
+        let at = Timestamp::new(1_760_000_000);
+        let mut attributes = Attributes::default();
+        attributes.parse_arg("#journal").unwrap();
+        attributes.parse_arg("parent:2").unwrap();
+        attributes.parse_arg("every:monthly").unwrap();
+        let add = Command::Add(String::from("Multi;line\n\"text\""), attributes);
+        journal.record(&add, Some(TaskId::new(4)), at).unwrap();
+        journal
+            .record(&Command::Do(TaskId::new(4).into()), None, at)
+            .unwrap();
+        let several = Selection::Ids(vec![TaskId::new(1), TaskId::new(3)]);
+        journal
+            .record(&Command::Delete(several.clone(), true), None, at)
+            .unwrap();
+        journal
+            .record(&Command::Delete(Selection::Done, false), None, at)
+            .unwrap();
+        let mut attributes = Attributes::default();
+        for arg in ["due:none", "-#journal", "every:none"] {
+            attributes.parse_arg(arg).unwrap();
+        }
+        let edit = Command::Edit(TaskId::new(4), None, attributes);
+        journal.record(&edit, None, at).unwrap();
+        let block = Command::Block(TaskId::new(4), TaskId::new(2));
+        journal.record(&block, None, at).unwrap();
+        let unblock = Command::Unblock(TaskId::new(4), None);
+        journal.record(&unblock, None, at).unwrap();
+        journal
+            .record(&Command::List(ListQuery::default()), None, at)
+            .unwrap();
+        assert_eq!(journal.len(), 6);
+
+        let replay = Journal::new(file).load().unwrap();
+        assert!(replay.warnings.is_empty());
+        let entry = |command, created| Entry {
+            command,
+            created,
+            at: Some(at),
+        };
+        assert_eq!(
+            replay.entries,
+            vec![
+                entry(add, Some(TaskId::new(4))),
+                entry(Command::Do(TaskId::new(4).into()), None),
+                entry(Command::Delete(several, true), None),
+                entry(edit, None),
+                entry(block, None),
+                entry(unblock, None),
+            ]
+        );
+
+        journal.clear().unwrap();
+        assert!(Journal::new(file).load().unwrap().entries.is_empty());
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_replays_entries_without_timestamps() {
+        let file = env::temp_dir().join(format!("todo_list_{}_old_journal", std::process::id()));
+        let file = file.to_str().unwrap();
+        fs::write(file, "add;1;Old task\ndo;1\n").unwrap();
+
+        let replay = Journal::new(file).load().unwrap();
+        assert!(replay.warnings.is_empty());
+        assert_eq!(
+            replay.entries[1],
+            Entry {
+                command: Command::Do(TaskId::new(1).into()),
+                created: None,
+                at: None,
+            }
+        );
+
+        fs::remove_file(file).unwrap();
+    }
+}
@@ -1,4 +1,21 @@
+pub mod activity;
+pub mod cli;
 pub mod command;
+pub mod completion;
+mod csv;
+pub mod date;
+pub mod exchange;
+pub mod history;
+pub mod journal;
 pub mod printer;
-mod task;
+pub mod projects;
+pub mod query;
+pub mod reminders;
+pub mod script;
+pub mod search;
+pub mod server;
+pub mod stats;
+pub mod store;
+pub mod task;
 pub mod task_list;
+mod tokenizer;
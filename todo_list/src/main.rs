@@ -1,36 +1,455 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::env;
 use std::io;
-use std::io::Write;
 use std::process;
 
+use rustyline::error::ReadlineError;
+use rustyline::history::FileHistory;
+use rustyline::Editor;
+use serde::Serialize;
+use todo_list::cli::Options;
 use todo_list::command::Command;
 use todo_list::command::build_command;
+use todo_list::completion::Completer;
+use todo_list::date::Date;
 use todo_list::printer::Printer;
-use todo_list::task_list::TaskList;
+use todo_list::projects::Projects;
+use todo_list::query::ListQuery;
+use todo_list::reminders::Reminders;
+use todo_list::reminders::DEFAULT_SOON_DAYS;
+use todo_list::script;
+use todo_list::script::Step;
+use todo_list::server;
+use todo_list::server::Server;
+use todo_list::stats::Stats;
+use todo_list::task::Task;
+use todo_list::task::TaskId;
+use todo_list::task::TaskStatus;
+use todo_list::task_list::Outcome;
+use todo_list::task_list::Report;
+use todo_list::task_list::TaskError;
 
-const TASK_FILE: &str = "tasks.csv";
+/// Exit code of a command that could not be executed
+const EXIT_FAILURE: i32 = 1;
+/// Exit code of a command that could not be built
+const EXIT_USAGE: i32 = 2;
 
 fn main() {
-    let printer = Box::new(Printer::new());
-    let mut task_list = match TaskList::new(Box::clone(&printer), TASK_FILE) {
-        Ok(tl) => tl,
+    let options = match Options::build(env::args()) {
+        Ok(o) => o,
+        Err(e) => {
+            Printer::new().error(&e);
+            process::exit(EXIT_USAGE);
+        },
+    };
+
+    let printer = Box::new(
+        Printer::new()
+            .with_quiet(options.quiet)
+            .with_json_lines(options.json_lines),
+    );
+
+    let mut projects = match Projects::open(Box::clone(&printer), &options.file) {
+        Ok(p) => p,
         Err(e) => {
             let msg = format!("Unable to create Task List due to previous error: {}", e);
             printer.error(&msg);
-            process::exit(1);
+            process::exit(EXIT_FAILURE);
+        }
+    };
+
+    if options.command == ["serve"] {
+        let port = options.port.unwrap_or(server::DEFAULT_PORT);
+        serve(&printer, projects, port);
+        process::exit(EXIT_FAILURE);
+    }
+
+    let code = match options.command_line() {
+        Some(input) => run_command(&printer, &mut projects, &input, options.json),
+        None => {
+            let history_file = format!("{}.prompt_history", options.file);
+            run_prompt(&printer, &mut projects, &history_file);
+            0
+        },
+    };
+
+    if let Err(e) = projects.snapshot() {
+        printer.error(format!("Unable to save tasks: {}", e).as_str());
+        process::exit(EXIT_FAILURE);
+    }
+
+    process::exit(code);
+}
+
+/// Runs a single command given as arguments, returning the exit code
+fn run_command(printer: &Printer, projects: &mut Projects, input: &str, json: bool) -> i32 {
+    let command = match build_command(input) {
+        Ok(c) => c,
+        Err(e) => {
+            printer.error(e.val().as_str());
+            return EXIT_USAGE;
+        }
+    };
+
+    if let Command::Run(file, keep_going) = command {
+        return run_script(printer, projects, &file, keep_going);
+    }
+
+    match projects.execute(command) {
+        Ok(Outcome::Listed(tasks)) if json => print_json(printer, &tasks, 0),
+        Ok(Outcome::Found(matches)) if json => {
+            let tasks: Vec<_> = matches.into_iter().map(|m| m.task).collect();
+            print_json(printer, &tasks, 0)
+        },
+        Ok(Outcome::Reminders(reminders)) if json => {
+            let code = if reminders.overdue.is_empty() {
+                0
+            } else {
+                EXIT_FAILURE
+            };
+            print_json(printer, &reminders, code)
+        },
+        Ok(Outcome::ListedAll(lists)) if json => {
+            let lists: BTreeMap<_, _> = lists.into_iter().collect();
+            print_json(printer, &lists, 0)
+        },
+        result => report(printer, result, projects.current()),
+    }
+}
+
+/// Prints the result of a command as JSON, returning the given exit code
+/// unless it could not be printed
+fn print_json(printer: &Printer, value: &impl Serialize, code: i32) -> i32 {
+    match printer.json(value) {
+        Ok(()) => code,
+        Err(e) => {
+            printer.error(format!("Unable to print JSON: {}", e).as_str());
+            EXIT_FAILURE
+        },
+    }
+}
+
+/// Runs the commands of a script file, returning the exit code.
+/// Unless told to keep going, nothing is run when any line is invalid and the
+/// script stops at the first command that fails.
+fn run_script(printer: &Printer, projects: &mut Projects, file: &str, keep_going: bool) -> i32 {
+    let steps = match script::read(file) {
+        Ok(s) => s,
+        Err(e) => {
+            printer.error(format!("Unable to read script {}: {}", file, e).as_str());
+            return EXIT_FAILURE;
+        },
+    };
+
+    let invalid: Vec<_> = steps
+        .iter()
+        .filter_map(|s| step_error(s).map(|e| (s.line, e)))
+        .collect();
+    if !keep_going && !invalid.is_empty() {
+        for (line, e) in invalid {
+            printer.error(format!("{}:{}: {}", file, line, e).as_str());
+        }
+        return EXIT_USAGE;
+    }
+
+    let mut code = 0;
+    for step in steps {
+        if let Some(e) = step_error(&step) {
+            printer.error(format!("{}:{}: {}", file, step.line, e).as_str());
+            code = EXIT_USAGE;
+            continue;
+        }
+
+        let result = match step.command {
+            Ok(Command::Exit) => break,
+            Ok(command) => projects.execute(command),
+            Err(_) => continue,
+        };
+
+        let step_code = report(printer, result, projects.current());
+        if step_code != 0 {
+            code = step_code;
+            if !keep_going {
+                printer.error(format!("Script stopped at {}:{}", file, step.line).as_str());
+                break;
+            }
         }
+    }
+
+    code
+}
+
+/// Why a line of a script can not be run, if it can not
+fn step_error(step: &Step) -> Option<String> {
+    match &step.command {
+        Ok(Command::Run(_, _)) => Some(String::from("Scripts can not run other scripts")),
+        Ok(_) => None,
+        Err(e) => Some(e.val()),
+    }
+}
+
+/// Serves the list in use over HTTP, only returning if that is not possible
+fn serve(printer: &Printer, projects: Projects, port: u16) {
+    let server = match Server::bind(Box::new(printer.clone()), port, projects.into_current_list()) {
+        Ok(s) => s,
+        Err(e) => {
+            printer.error(format!("Unable to listen on port {}: {}", port, e).as_str());
+            return;
+        },
+    };
+
+    if let Ok(addr) = server.local_addr() {
+        printer.notice(&format!("Serving tasks on http://{}", addr));
+    }
+    server.run();
+}
+
+fn join_ids(ids: &[TaskId]) -> String {
+    let ids: Vec<_> = ids.iter().map(|id| id.to_string()).collect();
+    ids.join(", ")
+}
+
+/// Prints listed tasks, the blocked ones apart after the rest
+fn print_listed(printer: &Printer, tasks: &[Task]) {
+    let (blocked, rest): (Vec<_>, Vec<_>) = tasks
+        .iter()
+        .cloned()
+        .partition(|t| *t.status() == TaskStatus::Blocked);
+
+    print_tasks(printer, &rest);
+    if !blocked.is_empty() {
+        printer.output("Blocked:");
+        print_tasks(printer, &blocked);
+    }
+}
+
+/// Prints tasks with subtasks indented under their parent
+fn print_tasks(printer: &Printer, tasks: &[Task]) {
+    // Listed subtasks always follow their parent
+    let mut depths = HashMap::new();
+    for task in tasks {
+        let depth = task
+            .parent()
+            .and_then(|p| depths.get(p))
+            .map_or(0, |d| d + 1);
+        depths.insert(*task.id(), depth);
+        printer.task(task, depth);
+    }
+}
+
+/// Prints the result of a command, returning the exit code
+fn report(printer: &Printer, result: Result<Outcome, TaskError>, current: &str) -> i32 {
+    let outcome = match result {
+        Ok(o) => o,
+        Err(e @ TaskError::Io(_)) => {
+            printer.error(&e.to_string());
+            return EXIT_FAILURE;
+        },
+        Err(e) => {
+            printer.warning(&e.to_string());
+            return EXIT_FAILURE;
+        },
+    };
+
+    match outcome {
+        Outcome::Listed(tasks) => print_listed(printer, &tasks),
+        Outcome::ListedAll(lists) => {
+            for (name, tasks) in lists {
+                printer.output(&format!("[{}]", name));
+                print_listed(printer, &tasks);
+            }
+        },
+        Outcome::Created(id) => {
+            printer.notice(&format!("Task successfully created with id {}", id));
+        },
+        Outcome::Updated(report) => return report_tasks(printer, &report),
+        Outcome::Deleted(report) => {
+            if report.succeeded.is_empty() && report.failed.is_empty() {
+                printer.notice("No tasks to delete");
+            }
+            for id in &report.succeeded {
+                printer.notice(&format!("Task with key {} successfully deleted", id));
+            }
+            return report_tasks(printer, &report);
+        },
+        Outcome::Renumbered(ids) if ids.is_empty() => {
+            printer.notice("Task ids are already consecutive");
+        },
+        Outcome::Renumbered(ids) => {
+            for (old, new) in ids {
+                printer.notice(&format!("Task with key {} is now {}", old, new));
+            }
+        },
+        Outcome::Changes(id, changes) if changes.is_empty() => {
+            printer.notice(&format!("No recorded changes for task with key {}", id));
+        },
+        Outcome::Changes(_, changes) => {
+            for change in changes {
+                let at = change.at.map_or(String::from("-"), |t| t.to_string());
+                let task = change.after.as_ref().or(change.before.as_ref()).unwrap();
+                printer.output(&format!("{}\t{}\t{}", at, change.summary(), task.text()));
+            }
+        },
+        Outcome::Stats(stats) => report_stats(printer, &stats),
+        Outcome::Reminders(reminders) if reminders.is_empty() => {
+            printer.notice("No tasks overdue or due soon");
+        },
+        Outcome::Reminders(reminders) => {
+            let sections = [
+                ("Overdue:", &reminders.overdue),
+                ("Due soon:", &reminders.due_soon),
+            ];
+            for (title, tasks) in sections {
+                if !tasks.is_empty() {
+                    printer.output(title);
+                    print_tasks(printer, tasks);
+                }
+            }
+
+            if !reminders.overdue.is_empty() {
+                return EXIT_FAILURE;
+            }
+        },
+        Outcome::Found(matches) if matches.is_empty() => printer.notice("No matching tasks"),
+        Outcome::Found(matches) => {
+            for found in matches {
+                printer.found(&found.task, &found.ranges);
+            }
+        },
+        Outcome::Switched(name) => {
+            printer.notice(&format!("Using list {}", name));
+        },
+        Outcome::Lists(names) => {
+            for name in names {
+                let marker = if name == current { "*" } else { " " };
+                printer.output(&format!("{} {}", marker, name));
+            }
+        },
+        Outcome::Moved(id, name, new_id) => {
+            printer.notice(&format!(
+                "Task with key {} moved to list {} as {}",
+                id, name, new_id
+            ));
+        },
+        Outcome::Exported(file, count) => {
+            printer.notice(&format!("Exported {} task(s) to {}", count, file));
+        },
+        Outcome::Imported(ids) if ids.is_empty() => printer.notice("No tasks to import"),
+        Outcome::Imported(ids) => {
+            printer.notice(&format!("Imported task(s) with id {}", join_ids(&ids)));
+        },
+        Outcome::Reverted(ids) => {
+            printer.notice(&format!("Reverted changes to task(s) {}", join_ids(&ids)));
+        },
+        Outcome::Redone(ids) => {
+            printer.notice(&format!("Redone changes to task(s) {}", join_ids(&ids)));
+        },
+        Outcome::Ignored => (),
+    }
+
+    0
+}
+
+fn report_stats(printer: &Printer, stats: &Stats) {
+    let average = match stats.average_time_to_finish {
+        Some(seconds) => format_duration(seconds),
+        None => {
+            printer.notice("No completed tasks yet");
+            return;
+        },
+    };
+
+    printer.output("Completed per day:");
+    for (day, count) in &stats.per_day {
+        printer.output(&format!("  {}\t{}", day, count));
+    }
+    printer.output("Completed per week:");
+    for (monday, count) in &stats.per_week {
+        printer.output(&format!("  Week of {}\t{}", monday, count));
+    }
+    printer.output(&format!("Average time to finish: {}", average));
+}
+
+/// Duration like `2d 3h 15m`, dropping the leading zero units
+fn format_duration(seconds: u64) -> String {
+    let units = [
+        (seconds / 86_400, "d"),
+        (seconds % 86_400 / 3600, "h"),
+        (seconds % 3600 / 60, "m"),
+    ];
+    let pieces: Vec<_> = units
+        .iter()
+        .skip_while(|(value, _)| *value == 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+
+    match pieces.is_empty() {
+        true => String::from("less than a minute"),
+        false => pieces.join(" "),
+    }
+}
+
+/// Prints the tasks a command created along the way and warns about the ones
+/// it could not act on, returning the exit code
+fn report_tasks(printer: &Printer, report: &Report) -> i32 {
+    for id in &report.created {
+        printer.notice(&format!("Next occurrence created with id {}", id));
+    }
+
+    for (_, e) in &report.failed {
+        printer.warning(&e.to_string());
+    }
+
+    if report.failed.is_empty() {
+        0
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+fn run_prompt(printer: &Printer, projects: &mut Projects, history_file: &str) {
+    let mut editor = match Editor::new() {
+        Ok(e) => e,
+        Err(e) => {
+            printer.error(format!("Unable to start the prompt: {}", e).as_str());
+            return;
+        },
     };
+    editor.set_helper(Some(Completer::default()));
+
+    match editor.load_history(history_file) {
+        Err(ReadlineError::Io(e)) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => printer.warning(format!("Unable to load prompt history: {}", e).as_str()),
+        Ok(_) => (),
+    }
 
     printer.notice("Welcome to the task manager!");
+    // List the reminders were last given for and which ones
+    let mut reminded = (String::new(), Reminders::default());
+    remind(printer, projects, &mut reminded);
 
     'main: loop {
-        let input = match ask_user_input() {
-            Ok(i) => i,
+        let input = match ask_user_input(&mut editor, projects) {
+            Ok(Some(i)) => i,
+            Ok(None) => {
+                printer.end_prompt();
+                break 'main;
+            },
+            // The line was cancelled with Ctrl-C
+            Err(ReadlineError::Interrupted) => continue,
             Err(e) => {
                 printer.error(format!("Unable to read input: {:?}", e).as_str());
                 continue;
             }
         };
 
+        if editor.add_history_entry(input.as_str()).unwrap_or(false) {
+            if let Err(e) = editor.append_history(history_file) {
+                printer.warning(format!("Unable to save prompt history: {}", e).as_str());
+            }
+        }
+
         let command = match build_command(input.as_str()) {
             Ok(c) => c,
             Err(e) => {
@@ -44,18 +463,70 @@ fn main() {
             break 'main;
         }
 
-        task_list.execute(command);
+        if let Command::Run(file, keep_going) = command {
+            run_script(printer, projects, &file, keep_going);
+            continue;
+        }
+
+        let result = projects.execute(command);
+        report(printer, result, projects.current());
+        remind(printer, projects, &mut reminded);
     }
 
     printer.notice("Good bye!");
 }
 
-fn ask_user_input() -> Result<String, io::Error> {
-    print!("CLI > ");
-    let _ = io::stdout().flush();
+/// Warns about the tasks of the list in use that are overdue or due soon,
+/// leaving out the ones already warned about
+fn remind(printer: &Printer, projects: &Projects, reminded: &mut (String, Reminders)) {
+    let today = Date::today();
+    let tasks = projects.current_list().query(&ListQuery::default());
+    let reminders = Reminders::compute(tasks.into_iter(), today, DEFAULT_SOON_DAYS);
+
+    let new = match reminded.0 == projects.current() {
+        true => reminders.since(&reminded.1),
+        false => reminders.clone(),
+    };
+    for task in &new.overdue {
+        let due = task.due().unwrap();
+        printer.warning(&format!(
+            "Task with key {} is overdue since {}: {}",
+            task.id(),
+            due,
+            task.text()
+        ));
+    }
+    for task in &new.due_soon {
+        let due = match task.due() {
+            Some(d) if *d == today => String::from("today"),
+            Some(d) => format!("on {}", d),
+            None => continue,
+        };
+        printer.warning(&format!(
+            "Task with key {} is due {}: {}",
+            task.id(),
+            due,
+            task.text()
+        ));
+    }
+
+    *reminded = (String::from(projects.current()), reminders);
+}
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+/// It returns `None` once the input is closed.
+/// Tab completes the ids and tags of the tasks in the list in use.
+fn ask_user_input(
+    editor: &mut Editor<Completer, FileHistory>,
+    projects: &Projects,
+) -> Result<Option<String>, ReadlineError> {
+    if let Some(completer) = editor.helper_mut() {
+        let tasks = projects.current_list().query(&ListQuery::default());
+        completer.update(tasks.into_iter());
+    }
 
-    return Ok(String::from(input.trim()));
+    match editor.readline(&format!("CLI [{}] > ", projects.current())) {
+        Ok(input) => Ok(Some(String::from(input.trim()))),
+        Err(ReadlineError::Eof) => Ok(None),
+        Err(e) => Err(e),
+    }
 }
@@ -1,36 +1,113 @@
+use std::env;
+use std::io;
+use std::io::IsTerminal;
+use std::ops::Range;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::task::Task;
+
+/// Environment variable that turns colors off when set to anything, see
+/// <https://no-color.org>
+pub const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+
 enum LogLevel {
+    /// Part of what a command was asked to show
+    Output,
     Notice,
     Error,
     Warning,
 }
 
 impl LogLevel {
-    fn prefix(&self) -> String {
+    fn name(&self) -> &'static str {
         match self {
-            Self::Notice => String::from(""),
-            Self::Error => String::from("[\x1b[0;31mERROR\x1b[0m]"),
-            Self::Warning => String::from("[\x1b[0;33mWARNING\x1b[0m]"),
+            Self::Output => "output",
+            Self::Notice => "notice",
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+
+    fn prefix(&self, color: bool) -> String {
+        match (self, color) {
+            (Self::Output | Self::Notice, _) => String::from(""),
+            (Self::Error, true) => String::from("[\x1b[0;31mERROR\x1b[0m]"),
+            (Self::Error, false) => String::from("[ERROR]"),
+            (Self::Warning, true) => String::from("[\x1b[0;33mWARNING\x1b[0m]"),
+            (Self::Warning, false) => String::from("[WARNING]"),
         }
     }
 }
 
+/// Writes messages and tasks to the standard output.
+///
+/// Colors are used only when it is a terminal and [NO_COLOR_ENV_VAR] is not
+/// set. Quiet printers leave notices out, and JSON lines printers write every
+/// message and task as a JSON object on its own line.
+/// Whole results can be printed as a single JSON document instead, see
+/// [Printer::json].
 #[derive(Clone, Debug)]
-pub struct Printer {}
+pub struct Printer {
+    color: bool,
+    quiet: bool,
+    json_lines: bool,
+}
 
 impl Printer {
     pub fn new() -> Self {
-        Self {}
+        let no_color = env::var_os(NO_COLOR_ENV_VAR).is_some_and(|v| !v.is_empty());
+
+        Self {
+            color: io::stdout().is_terminal() && !no_color,
+            quiet: false,
+            json_lines: false,
+        }
     }
 
-    fn print(&self, level: LogLevel, msg: &str) {
-        let prefix = level.prefix();
+    pub fn with_color(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+
+    pub fn with_quiet(self, quiet: bool) -> Self {
+        Self { quiet, ..self }
+    }
+
+    pub fn with_json_lines(self, json_lines: bool) -> Self {
+        Self { json_lines, ..self }
+    }
+
+    /// The line printed for the given message, if any
+    fn line(&self, level: LogLevel, msg: &str) -> Option<String> {
+        if self.quiet && matches!(level, LogLevel::Notice) {
+            return None;
+        }
+
+        if self.json_lines {
+            return Some(json!({ "level": level.name(), "message": msg }).to_string());
+        }
+
+        let prefix = level.prefix(self.color);
         if prefix.is_empty() {
-            println!("{}", msg)
+            Some(String::from(msg))
         } else {
-            println!("{}: {}", level.prefix(), msg)
+            Some(format!("{}: {}", prefix, msg))
+        }
+    }
+
+    fn print(&self, level: LogLevel, msg: &str) {
+        if let Some(line) = self.line(level, msg) {
+            println!("{}", line)
         }
     }
 
+    /// Prints part of what a command was asked to show, like its statistics,
+    /// even when quiet
+    pub fn output(&self, msg: &str) {
+        self.print(LogLevel::Output, msg)
+    }
+
     pub fn notice(&self, msg: &str) {
         self.print(LogLevel::Notice, msg)
     }
@@ -42,6 +119,84 @@ impl Printer {
     pub fn warning(&self, msg: &str) {
         self.print(LogLevel::Warning, msg)
     }
+
+    /// Row printed for a task, see [Printer::task] and [Printer::found]
+    fn task_line(&self, task: &Task, depth: usize, matches: Option<&[Range<usize>]>) -> String {
+        if self.json_lines {
+            let mut line = json!({ "level": "task", "depth": depth, "task": task });
+            if let Some(matches) = matches {
+                let matches: Vec<_> = matches.iter().map(|m| [m.start, m.end]).collect();
+                line["matches"] = json!(matches);
+            }
+
+            return line.to_string();
+        }
+
+        let mut row = format!(
+            "{}\t{}\t\t{}{}",
+            task.id(),
+            task.status().val(),
+            "  ".repeat(depth),
+            self.highlight(task.text(), matches.unwrap_or_default())
+        );
+
+        let attributes = task.attributes();
+        if !attributes.is_empty() {
+            row += format!("\t{}", attributes).as_str();
+        }
+
+        if !task.blockers().is_empty() {
+            let blockers: Vec<_> = task.blockers().iter().map(|b| b.to_string()).collect();
+            row += format!("\tblocked on {}", blockers.join(", ")).as_str();
+        }
+
+        row
+    }
+
+    /// Prints a task as a single row of a listing, with its text indented as
+    /// deep as it is nested under other listed tasks.
+    /// Tasks are printed even by quiet printers.
+    pub fn task(&self, task: &Task, depth: usize) {
+        println!("{}", self.task_line(task, depth, None))
+    }
+
+    /// Prints a task found by a search, highlighting the byte ranges of its
+    /// text that matched
+    pub fn found(&self, task: &Task, matches: &[Range<usize>]) {
+        println!("{}", self.task_line(task, 0, Some(matches)))
+    }
+
+    /// Prints a value as one pretty JSON document, like the tasks `list` shows
+    /// with `--json`, even when quiet
+    pub fn json(&self, value: &impl Serialize) -> Result<(), serde_json::Error> {
+        println!("{}", serde_json::to_string_pretty(value)?);
+        Ok(())
+    }
+
+    /// Ends the line the interactive prompt left open once its input is
+    /// closed, unless printing JSON lines
+    pub fn end_prompt(&self) {
+        if !self.json_lines {
+            println!();
+        }
+    }
+
+    /// Text with the given byte ranges in bold yellow, as is without colors
+    fn highlight(&self, text: &str, matches: &[Range<usize>]) -> String {
+        if !self.color {
+            return String::from(text);
+        }
+
+        let mut highlighted = String::new();
+        let mut end = 0;
+        for range in matches {
+            highlighted += &text[end..range.start];
+            highlighted += &format!("\x1b[1;33m{}\x1b[0m", &text[range.clone()]);
+            end = range.end;
+        }
+
+        highlighted + &text[end..]
+    }
 }
 
 impl Default for Printer {
@@ -49,3 +204,82 @@ impl Default for Printer {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::TaskId;
+
+    fn printer() -> Printer {
+        Printer::new().with_color(false)
+    }
+
+    #[test]
+    fn it_colors_prefixes_only_when_asked() {
+        assert_eq!(
+            printer().line(LogLevel::Warning, "Careful"),
+            Some(String::from("[WARNING]: Careful"))
+        );
+        assert_eq!(
+            printer().with_color(true).line(LogLevel::Error, "Failed"),
+            Some(String::from("[\x1b[0;31mERROR\x1b[0m]: Failed"))
+        );
+        assert_eq!(
+            printer().line(LogLevel::Notice, "Done"),
+            Some(String::from("Done"))
+        );
+    }
+
+    #[test]
+    fn it_leaves_notices_out_when_quiet() {
+        let printer = printer().with_quiet(true);
+        assert_eq!(printer.line(LogLevel::Notice, "Done"), None);
+        assert_eq!(
+            printer.line(LogLevel::Output, "Completed per day:"),
+            Some(String::from("Completed per day:"))
+        );
+        assert_eq!(
+            printer.line(LogLevel::Error, "Failed"),
+            Some(String::from("[ERROR]: Failed"))
+        );
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn it_prints_json_lines() {
+        let printer = printer().with_json_lines(true);
+        assert_eq!(
+            printer.line(LogLevel::Warning, "Say \"hi\""),
+            Some(String::from(
+                r#"{"level":"warning","message":"Say \"hi\""}"#
+            ))
+        );
+
+        let line = printer.task_line(&Task::new(TaskId::new(3), "Buy milk"), 1, None);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["level"], "task");
+        assert_eq!(value["depth"], 1);
+        assert_eq!(value["task"]["id"], 3);
+        assert_eq!(value["task"]["text"], "Buy milk");
+        assert!(value.get("matches").is_none());
+
+        let line = printer.task_line(&Task::new(TaskId::new(3), "Buy milk"), 0, Some(&[4..8]));
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["matches"], serde_json::json!([[4, 8]]));
+    }
+
+    #[test]
+    fn it_highlights_matches_only_with_colors() {
+        let task = Task::new(TaskId::new(3), "Buy milk");
+        assert_eq!(
+            printer().task_line(&task, 0, Some(&[0..1, 4..8])),
+            "3\tpending\t\tBuy milk"
+        );
+        assert_eq!(
+            printer()
+                .with_color(true)
+                .task_line(&task, 0, Some(&[0..1, 4..8])),
+            "3\tpending\t\t\x1b[1;33mB\x1b[0muy \x1b[1;33mmilk\x1b[0m"
+        );
+    }
+}
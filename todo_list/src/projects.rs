@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::activity::Activity;
+use crate::command::Command;
+use crate::history::History;
+use crate::journal::Journal;
+use crate::printer::Printer;
+use crate::query::ListQuery;
+use crate::store::CsvStore;
+use crate::task::TaskId;
+use crate::task::TaskStatus;
+use crate::task_list::Outcome;
+use crate::task_list::TaskError;
+use crate::task_list::TaskList;
+
+/// Extension of the task file of every list
+const LIST_EXTENSION: &str = "csv";
+
+/// Named [TaskList]s living next to each other, one task file per list.
+///
+/// Commands act on the list in use, except the ones about lists themselves
+/// like [Command::Use] or [Command::Move].
+/// Lists are opened the first time they are used and kept open afterwards.
+#[derive(Debug)]
+pub struct Projects {
+    dir: PathBuf,
+    current: String,
+    /// Task file of every list, they are `{dir}/{name}.csv` unless given
+    files: BTreeMap<String, String>,
+    lists: BTreeMap<String, TaskList>,
+    printer: Box<Printer>,
+}
+
+impl Projects {
+    /// Opens the list stored in the given task file, named after it.
+    /// Every other list lives in the same directory.
+    pub fn open(printer: Box<Printer>, file: &str) -> Result<Self, io::Error> {
+        let path = Path::new(file);
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let name = path
+            .file_stem()
+            .and_then(|n| n.to_str())
+            .unwrap_or("tasks");
+
+        let mut projects = Self {
+            dir,
+            current: String::from(name),
+            files: BTreeMap::from([(String::from(name), String::from(file))]),
+            lists: BTreeMap::new(),
+            printer,
+        };
+        projects.list(name)?;
+
+        Ok(projects)
+    }
+
+    /// Name of the list in use
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// The list in use
+    pub fn current_list(&self) -> &TaskList {
+        &self.lists[&self.current]
+    }
+
+    /// Closes every list but the one in use, returning it
+    pub fn into_current_list(mut self) -> TaskList {
+        self.lists.remove(&self.current).unwrap()
+    }
+
+    /// Names of every list, including the ones without a task file yet.
+    /// Other CSV files in the directory are not lists.
+    pub fn names(&self) -> Result<Vec<String>, io::Error> {
+        let mut names: Vec<_> = self.files.keys().cloned().collect();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(LIST_EXTENSION)
+                || !CsvStore::is_task_file(&path)
+            {
+                continue;
+            }
+
+            match path.file_stem().and_then(|n| n.to_str()) {
+                Some(name) if !name.starts_with('.') && !names.iter().any(|n| n == name) => {
+                    names.push(String::from(name))
+                },
+                _ => (),
+            }
+        }
+
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn execute(&mut self, command: Command) -> Result<Outcome, TaskError> {
+        match command {
+            Command::Use(name) => {
+                self.list(&name)?;
+                self.current = name.clone();
+                Ok(Outcome::Switched(name))
+            },
+            Command::Lists => Ok(Outcome::Lists(self.names()?)),
+            Command::Move(id, name) => self.move_task(id, name),
+            Command::List(query) if query.all_lists => {
+                let mut listed = vec![];
+                for name in self.names()? {
+                    let query = ListQuery {
+                        all_lists: false,
+                        ..query.clone()
+                    };
+
+                    // Lists that are not open are only read, never saved
+                    let outcome = match self.lists.get_mut(&name) {
+                        Some(list) => list.execute(Command::List(query))?,
+                        None => self.read_only_list(&name)?.execute(Command::List(query))?,
+                    };
+                    match outcome {
+                        Outcome::Listed(tasks) if tasks.is_empty() => (),
+                        Outcome::Listed(tasks) => listed.push((name, tasks)),
+                        _ => (),
+                    }
+                }
+
+                Ok(Outcome::ListedAll(listed))
+            },
+            command => {
+                let current = self.current.clone();
+                self.list(&current)?.execute(command)
+            },
+        }
+    }
+
+    /// Saves the tasks of every open list
+    pub fn snapshot(&mut self) -> Result<(), io::Error> {
+        for list in self.lists.values_mut() {
+            list.snapshot()?;
+        }
+
+        Ok(())
+    }
+
+    fn file(&self, name: &str) -> String {
+        match self.files.get(name) {
+            Some(file) => file.clone(),
+            None => {
+                let file = self.dir.join(format!("{}.{}", name, LIST_EXTENSION));
+                file.to_string_lossy().into_owned()
+            },
+        }
+    }
+
+    fn list(&mut self, name: &str) -> Result<&mut TaskList, io::Error> {
+        if !self.lists.contains_key(name) {
+            let file = self.file(name);
+            self.files.insert(String::from(name), file.clone());
+
+            let list = TaskList::new(Box::clone(&self.printer), &file)
+                .and_then(|tl| tl.with_journal(Journal::new(&format!("{}.journal", file))))
+                .and_then(|tl| tl.with_history(History::persistent(&format!("{}.history", file))))
+                .map(|tl| tl.with_activity(Activity::persistent(&format!("{}.activity", file))))?;
+            self.lists.insert(String::from(name), list);
+        }
+
+        Ok(self.lists.get_mut(name).unwrap())
+    }
+
+    /// Opens a list without keeping it open, so it is never saved
+    fn read_only_list(&self, name: &str) -> Result<TaskList, io::Error> {
+        let file = self.file(name);
+
+        TaskList::new(Box::clone(&self.printer), &file)
+            .and_then(|tl| tl.with_journal_replayed(Journal::new(&format!("{}.journal", file))))
+    }
+
+    /// Recreates the task in the given list under a new id, then deletes it
+    /// from the one in use
+    fn move_task(&mut self, id: TaskId, to: String) -> Result<Outcome, TaskError> {
+        let from = self.current.clone();
+        if from == to {
+            return Err(TaskError::AlreadyInList(to));
+        }
+
+        let task = match self.list(&from)?.execute(Command::List(ListQuery::default()))? {
+            Outcome::Listed(tasks) => tasks.into_iter().find(|t| t.id() == &id),
+            _ => None,
+        };
+        let task = task.ok_or(TaskError::UnknownTask(id))?;
+
+        self.list(&from)?
+            .execute(Command::Delete(id.into(), false))?;
+
+        // Its parent stays behind
+        let mut attributes = task.attributes();
+        attributes.parent = None;
+        let created = self
+            .list(&to)
+            .map_err(TaskError::from)
+            .and_then(|list| list.execute(Command::Add(String::from(task.text()), attributes)));
+        let new_id = match created {
+            Ok(Outcome::Created(new_id)) => new_id,
+            Ok(_) => unreachable!("Adding a task always creates it"),
+            Err(e) => {
+                self.list(&from)?.execute(Command::Revert)?;
+                return Err(e);
+            },
+        };
+
+        if task.status() == &TaskStatus::Done {
+            self.list(&to)?.execute(Command::Do(new_id.into()))?;
+        }
+
+        Ok(Outcome::Moved(id, to, new_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::*;
+    use crate::task::Attributes;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("todo_list_{}_{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+
+    fn add(text: &str) -> Command {
+        Command::Add(String::from(text), Attributes::default())
+    }
+
+    #[test]
+    fn it_switches_between_lists() {
+        let dir = temp_dir("projects");
+        let file = dir.join("work.csv");
+        let mut projects = Projects::open(Box::default(), file.to_str().unwrap()).unwrap();
+        assert_eq!(projects.current(), "work");
+
+        projects.execute(add("Write report")).unwrap();
+        assert_eq!(
+            projects.execute(Command::Use(String::from("home"))).unwrap(),
+            Outcome::Switched(String::from("home"))
+        );
+        projects.execute(add("Buy milk")).unwrap();
+        projects.execute(add("Water plants")).unwrap();
+        projects.snapshot().unwrap();
+
+        assert_eq!(
+            projects.execute(Command::Lists).unwrap(),
+            Outcome::Lists(vec![String::from("home"), String::from("work")])
+        );
+
+        let all = ListQuery {
+            all_lists: true,
+            ..ListQuery::default()
+        };
+        match projects.execute(Command::List(all)).unwrap() {
+            Outcome::ListedAll(lists) => {
+                let counts: Vec<_> = lists.iter().map(|(n, t)| (n.as_str(), t.len())).collect();
+                assert_eq!(counts, vec![("home", 2), ("work", 1)]);
+            },
+            other => panic!("Unexpected list result {:?}", other),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_leaves_other_files_and_unused_lists_alone() {
+        let dir = temp_dir("other_files");
+        let contacts = "Name;Email\nBob;bob@example.com\n";
+        fs::write(dir.join("contacts.csv"), contacts).unwrap();
+        let home = "next_id;2\n1;pending;Buy milk\nnot a task\n";
+        fs::write(dir.join("home.csv"), home).unwrap();
+
+        let file = dir.join("work.csv");
+        let mut projects = Projects::open(Box::default(), file.to_str().unwrap()).unwrap();
+        assert_eq!(
+            projects.execute(Command::Lists).unwrap(),
+            Outcome::Lists(vec![String::from("home"), String::from("work")])
+        );
+
+        let all = ListQuery {
+            all_lists: true,
+            ..ListQuery::default()
+        };
+        match projects.execute(Command::List(all)).unwrap() {
+            Outcome::ListedAll(lists) => assert_eq!(lists.len(), 1),
+            other => panic!("Unexpected list result {:?}", other),
+        }
+        projects.snapshot().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join("contacts.csv")).unwrap(),
+            contacts
+        );
+        assert!(!dir.join("contacts.csv.journal").exists());
+        assert!(!dir.join("contacts.csv.lock").exists());
+        assert_eq!(fs::read_to_string(dir.join("home.csv")).unwrap(), home);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_moves_tasks_between_lists() {
+        let dir = temp_dir("move");
+        let file = dir.join("work.csv");
+        let mut projects = Projects::open(Box::default(), file.to_str().unwrap()).unwrap();
+
+        projects.execute(add("Write report")).unwrap();
+        projects.execute(add("Buy milk")).unwrap();
+        projects
+            .execute(Command::Do(TaskId::new(2).into()))
+            .unwrap();
+
+        assert_eq!(
+            projects
+                .execute(Command::Move(TaskId::new(2), String::from("home")))
+                .unwrap(),
+            Outcome::Moved(TaskId::new(2), String::from("home"), TaskId::new(1))
+        );
+        assert_eq!(
+            projects.execute(Command::Move(TaskId::new(2), String::from("home"))),
+            Err(TaskError::UnknownTask(TaskId::new(2)))
+        );
+        assert_eq!(
+            projects.execute(Command::Move(TaskId::new(1), String::from("work"))),
+            Err(TaskError::AlreadyInList(String::from("work")))
+        );
+
+        projects.execute(Command::Use(String::from("home"))).unwrap();
+        match projects
+            .execute(Command::List(ListQuery::default()))
+            .unwrap()
+        {
+            Outcome::Listed(tasks) => {
+                assert_eq!(tasks.len(), 1);
+                assert_eq!(tasks[0].text(), "Buy milk");
+                assert_eq!(tasks[0].status(), &TaskStatus::Done);
+            },
+            other => panic!("Unexpected list result {:?}", other),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
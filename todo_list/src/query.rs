@@ -0,0 +1,274 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+
+use crate::date::Date;
+use crate::task::Task;
+use crate::task::TaskId;
+use crate::task::TaskStatus;
+
+/// Order in which listed tasks are shown.
+/// Ties are always broken by task id.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum SortKey {
+    #[default]
+    Id,
+    /// Most urgent first
+    Priority,
+    /// Closest due date first, tasks without one last
+    Due,
+    Text,
+}
+
+impl SortKey {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "id" => Some(Self::Id),
+            "priority" | "prio" => Some(Self::Priority),
+            "due" => Some(Self::Due),
+            "text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Comparison of a task due date against a given one, as in `due<2026-11-01`
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum DueFilter {
+    Before(Date),
+    BeforeOrOn(Date),
+    On(Date),
+    OnOrAfter(Date),
+    After(Date),
+}
+
+impl DueFilter {
+    /// Parses the part after `due`, like `<2026-11-01` or `>=2026-11-01`
+    pub fn parse(input: &str) -> Option<Self> {
+        let (build, date): (fn(Date) -> Self, &str) = if let Some(d) = input.strip_prefix("<=") {
+            (Self::BeforeOrOn, d)
+        } else if let Some(d) = input.strip_prefix(">=") {
+            (Self::OnOrAfter, d)
+        } else if let Some(d) = input.strip_prefix('<') {
+            (Self::Before, d)
+        } else if let Some(d) = input.strip_prefix('>') {
+            (Self::After, d)
+        } else if let Some(d) = input.strip_prefix('=').or(input.strip_prefix(':')) {
+            (Self::On, d)
+        } else {
+            return None;
+        };
+
+        Some(build(Date::parse(date)?))
+    }
+
+    pub fn matches(&self, due: &Date) -> bool {
+        match self {
+            Self::Before(date) => due < date,
+            Self::BeforeOrOn(date) => due <= date,
+            Self::On(date) => due == date,
+            Self::OnOrAfter(date) => due >= date,
+            Self::After(date) => due > date,
+        }
+    }
+}
+
+/// Filters and order of a `list` command.
+/// Every given filter must match for a task to be listed.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct ListQuery {
+    pub status: Option<TaskStatus>,
+    pub tags: BTreeSet<String>,
+    pub due: Vec<DueFilter>,
+    /// Case insensitive text the task must contain
+    pub search: Option<String>,
+    pub sort: SortKey,
+    /// Lists the tasks of every list instead of the one in use, see
+    /// [Projects](crate::projects::Projects)
+    pub all_lists: bool,
+}
+
+impl ListQuery {
+    pub fn matches(&self, task: &Task) -> bool {
+        if self.status.as_ref().is_some_and(|s| s != task.status()) {
+            return false;
+        }
+
+        if !self.tags.is_subset(task.tags()) {
+            return false;
+        }
+
+        if !self.due.is_empty() {
+            match task.due() {
+                Some(due) if self.due.iter().all(|f| f.matches(due)) => (),
+                _ => return false,
+            }
+        }
+
+        match &self.search {
+            Some(search) => task.text().to_lowercase().contains(&search.to_lowercase()),
+            None => true,
+        }
+    }
+
+    /// Keeps the matching tasks in the requested order.
+    /// Subtasks follow their parent when it matches as well.
+    pub fn apply<'a>(&self, tasks: impl Iterator<Item = &'a Task>) -> Vec<&'a Task> {
+        let mut tasks: Vec<_> = tasks.filter(|t| self.matches(t)).collect();
+        tasks.sort_by(|a, b| self.compare(a, b).then(a.id().cmp(b.id())));
+
+        let ids: HashSet<_> = tasks.iter().map(|t| *t.id()).collect();
+        let mut visited = HashSet::new();
+        let mut ordered = Vec::with_capacity(tasks.len());
+
+        for task in &tasks {
+            if task.parent().is_none_or(|p| !ids.contains(p)) {
+                push_tree(task, &tasks, &mut visited, &mut ordered);
+            }
+        }
+        // Tasks whose parents loop back to them have no root to be reached from
+        for task in &tasks {
+            push_tree(task, &tasks, &mut visited, &mut ordered);
+        }
+
+        ordered
+    }
+
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        match self.sort {
+            SortKey::Id => Ordering::Equal,
+            SortKey::Priority => b.priority().cmp(a.priority()),
+            SortKey::Due => match (a.due(), b.due()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            },
+            SortKey::Text => a.text().to_lowercase().cmp(&b.text().to_lowercase()),
+        }
+    }
+}
+
+fn push_tree<'a>(
+    task: &'a Task,
+    tasks: &[&'a Task],
+    visited: &mut HashSet<TaskId>,
+    ordered: &mut Vec<&'a Task>,
+) {
+    if !visited.insert(*task.id()) {
+        return;
+    }
+
+    ordered.push(task);
+    for subtask in tasks.iter().filter(|t| t.parent() == Some(task.id())) {
+        push_tree(subtask, tasks, visited, ordered);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::Attributes;
+    use crate::task::TaskId;
+
+    fn task(id: usize, text: &str, args: &[&str]) -> Task {
+        let mut attributes = Attributes::default();
+        for arg in args {
+            attributes.parse_arg(arg).unwrap();
+        }
+
+        let mut task = Task::new(TaskId::new(id), text);
+        task.set_attributes(&attributes);
+        task
+    }
+
+    fn ids(tasks: Vec<&Task>) -> Vec<usize> {
+        tasks.iter().map(|t| t.id().val()).collect()
+    }
+
+    #[test]
+    fn it_filters_tasks() {
+        let mut done = task(3, "Done one", &["#work"]);
+        done.r#do();
+        let tasks = [
+            task(1, "Buy milk", &["due:2026-10-01", "#home"]),
+            task(2, "Write report", &["due:2026-11-15", "#work"]),
+            done,
+        ];
+
+        let query = ListQuery::default();
+        assert_eq!(ids(query.apply(tasks.iter())), vec![1, 2, 3]);
+
+        let query = ListQuery {
+            status: Some(TaskStatus::Pending),
+            ..ListQuery::default()
+        };
+        assert_eq!(ids(query.apply(tasks.iter())), vec![1, 2]);
+
+        let query = ListQuery {
+            tags: BTreeSet::from([String::from("work")]),
+            ..ListQuery::default()
+        };
+        assert_eq!(ids(query.apply(tasks.iter())), vec![2, 3]);
+
+        let query = ListQuery {
+            due: vec![DueFilter::parse("<2026-11-01").unwrap()],
+            ..ListQuery::default()
+        };
+        assert_eq!(ids(query.apply(tasks.iter())), vec![1]);
+
+        let query = ListQuery {
+            search: Some(String::from("REPORT")),
+            ..ListQuery::default()
+        };
+        assert_eq!(ids(query.apply(tasks.iter())), vec![2]);
+    }
+
+    #[test]
+    fn it_sorts_tasks() {
+        let tasks = [
+            task(1, "b", &["prio:low"]),
+            task(2, "C", &["due:2026-12-01"]),
+            task(3, "a", &["prio:urgent", "due:2026-11-01"]),
+            task(4, "d", &["prio:urgent"]),
+        ];
+
+        let sorted = |sort| {
+            ids(ListQuery {
+                sort,
+                ..ListQuery::default()
+            }
+            .apply(tasks.iter()))
+        };
+        assert_eq!(sorted(SortKey::Id), vec![1, 2, 3, 4]);
+        assert_eq!(sorted(SortKey::Priority), vec![3, 4, 2, 1]);
+        assert_eq!(sorted(SortKey::Due), vec![3, 2, 1, 4]);
+        assert_eq!(sorted(SortKey::Text), vec![3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn it_lists_subtasks_after_their_parent() {
+        let tasks = [
+            task(1, "d", &[]),
+            task(2, "c", &["parent:4"]),
+            task(3, "b", &["parent:1"]),
+            task(4, "a", &["parent:1"]),
+            task(5, "e", &["parent:9"]),
+        ];
+
+        let query = ListQuery::default();
+        assert_eq!(ids(query.apply(tasks.iter())), vec![1, 3, 4, 2, 5]);
+
+        let query = ListQuery {
+            sort: SortKey::Text,
+            ..ListQuery::default()
+        };
+        assert_eq!(ids(query.apply(tasks.iter())), vec![1, 4, 2, 3, 5]);
+
+        let query = ListQuery {
+            search: Some(String::from("c")),
+            ..ListQuery::default()
+        };
+        assert_eq!(ids(query.apply(tasks.iter())), vec![2]);
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::date::Date;
+use crate::task::Task;
+use crate::task::TaskId;
+use crate::task::TaskStatus;
+
+/// Days after today a task is still due soon, so today and tomorrow
+pub const DEFAULT_SOON_DAYS: u32 = 1;
+
+/// Tasks not done yet that are past their due date or close to it, sorted by
+/// due date and then by id
+#[derive(PartialEq, Eq, Debug, Default, Clone, Serialize)]
+pub struct Reminders {
+    pub overdue: Vec<Task>,
+    /// Due today or within the given number of days
+    pub due_soon: Vec<Task>,
+}
+
+impl Reminders {
+    pub fn compute<'a>(tasks: impl Iterator<Item = &'a Task>, today: Date, days: u32) -> Self {
+        let last_day = today.add_days(days as i64);
+        let mut reminders = Self::default();
+
+        for task in tasks.filter(|t| *t.status() != TaskStatus::Done) {
+            match task.due() {
+                Some(due) if *due < today => reminders.overdue.push(task.clone()),
+                Some(due) if *due <= last_day => reminders.due_soon.push(task.clone()),
+                _ => (),
+            }
+        }
+
+        for tasks in [&mut reminders.overdue, &mut reminders.due_soon] {
+            tasks.sort_by(|a, b| a.due().cmp(&b.due()).then(a.id().cmp(b.id())));
+        }
+
+        reminders
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overdue.is_empty() && self.due_soon.is_empty()
+    }
+
+    /// The reminders not already in the previous ones, so a task is only
+    /// reminded of again once it becomes overdue
+    pub fn since(&self, previous: &Self) -> Self {
+        let ids = |tasks: &[Task]| tasks.iter().map(|t| *t.id()).collect::<HashSet<TaskId>>();
+        let (overdue, due_soon) = (ids(&previous.overdue), ids(&previous.due_soon));
+
+        Self {
+            overdue: self
+                .overdue
+                .iter()
+                .filter(|t| !overdue.contains(t.id()))
+                .cloned()
+                .collect(),
+            due_soon: self
+                .due_soon
+                .iter()
+                .filter(|t| !due_soon.contains(t.id()))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::Attributes;
+
+    fn task(id: usize, due: &str) -> Task {
+        let mut task = Task::new(TaskId::new(id), "Task");
+        let mut attributes = Attributes::default();
+        attributes.parse_arg(&format!("due:{}", due)).unwrap();
+        task.set_attributes(&attributes);
+
+        task
+    }
+
+    fn ids(tasks: &[Task]) -> Vec<usize> {
+        tasks.iter().map(|t| t.id().val()).collect()
+    }
+
+    #[test]
+    fn it_finds_overdue_tasks_and_tasks_due_soon() {
+        let mut done = task(6, "2026-10-01");
+        done.r#do();
+        let tasks = [
+            task(1, "2026-10-20"),
+            task(2, "2026-10-17"),
+            task(3, "2026-10-18"),
+            task(4, "2026-10-10"),
+            task(5, "2026-10-19"),
+            done,
+            Task::new(TaskId::new(7), "No due date"),
+        ];
+        let today = Date::new(2026, 10, 18).unwrap();
+
+        let reminders = Reminders::compute(tasks.iter(), today, DEFAULT_SOON_DAYS);
+        assert_eq!(ids(&reminders.overdue), vec![4, 2]);
+        assert_eq!(ids(&reminders.due_soon), vec![3, 5]);
+
+        let reminders = Reminders::compute(tasks.iter(), today, 0);
+        assert_eq!(ids(&reminders.due_soon), vec![3]);
+    }
+
+    #[test]
+    fn it_leaves_out_the_reminders_already_given() {
+        let tasks = [task(1, "2026-10-17"), task(2, "2026-10-18")];
+        let previous = Reminders::compute(tasks.iter(), Date::new(2026, 10, 18).unwrap(), 1);
+        let current = Reminders::compute(tasks.iter(), Date::new(2026, 10, 19).unwrap(), 1);
+
+        let new = current.since(&previous);
+        assert_eq!(ids(&new.overdue), vec![2]);
+        assert!(new.due_soon.is_empty());
+        assert!(current.since(&current).is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+//! Files of commands run by `run` and `source`, one command per line.
+//! Blank lines and lines starting with `#` are skipped.
+
+use std::fs;
+use std::io;
+
+use crate::command::build_command;
+use crate::command::BuildError;
+use crate::command::Command;
+
+/// A command of a script
+#[derive(PartialEq, Eq, Debug)]
+pub struct Step {
+    /// Line the command is at, from 1
+    pub line: usize,
+    pub command: Result<Command, BuildError>,
+}
+
+/// Builds the command of every line of the script
+pub fn parse(content: &str) -> Vec<Step> {
+    content
+        .lines()
+        .zip(1..)
+        .map(|(text, line)| (text.trim(), line))
+        .filter(|(text, _)| !text.is_empty() && !text.starts_with('#'))
+        .map(|(text, line)| Step {
+            line,
+            command: build_command(text),
+        })
+        .collect()
+}
+
+pub fn read(file: &str) -> Result<Vec<Step>, io::Error> {
+    Ok(parse(&fs::read_to_string(file)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::task::Attributes;
+
+    #[test]
+    fn it_builds_a_command_per_line() {
+        let script = "# Onboarding\n\nadd 'Get a laptop'\n  lsit\r\n  # Week one\nadd Meet the team #people\n";
+        let steps = parse(script);
+
+        let mut attributes = Attributes::default();
+        attributes.parse_arg("#people").unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                Step {
+                    line: 3,
+                    command: Ok(Command::Add(
+                        String::from("Get a laptop"),
+                        Attributes::default()
+                    )),
+                },
+                Step {
+                    line: 4,
+                    command: Err(BuildError::UnknownCommand),
+                },
+                Step {
+                    line: 6,
+                    command: Ok(Command::Add(String::from("Meet the team"), attributes)),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Ranked search of tasks by their text and tags, used by `find`.
+//!
+//! Fuzzy searches match every word of the query against the text or the tags
+//! of a task, its characters appearing in order but not necessarily next to
+//! each other. Whole words and characters found together rank higher.
+//! Regex searches rank tasks by how many times the pattern matches.
+
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::task::Task;
+
+/// Score of every matched character
+const CHAR_SCORE: u32 = 1;
+/// Extra score of a character right after the previous matched one
+const CONSECUTIVE_BONUS: u32 = 4;
+/// Extra score of a character starting a word
+const WORD_START_BONUS: u32 = 2;
+/// Extra score of a query word found with nothing in between
+const SUBSTRING_BONUS: u32 = 10;
+
+#[derive(Debug, Clone)]
+pub enum Search {
+    /// Words that must all match, ignoring case
+    Fuzzy(Vec<String>),
+    Regex(Regex),
+}
+
+impl Search {
+    /// Reads `regex:PATTERN` as a regex search and anything else as a fuzzy
+    /// one, failing on invalid patterns
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match input.strip_prefix("regex:") {
+            Some(pattern) => Regex::new(pattern)
+                .map(Self::Regex)
+                .map_err(|_| String::from(pattern)),
+            None => Ok(Self::Fuzzy(
+                input.split_whitespace().map(String::from).collect(),
+            )),
+        }
+    }
+
+    /// Tasks matching the search, most relevant first and then by id
+    pub fn apply<'a>(&self, tasks: impl Iterator<Item = &'a Task>) -> Vec<Match> {
+        let mut matches: Vec<_> = tasks.filter_map(|t| self.matches(t)).collect();
+        matches.sort_by(|a, b| b.score.cmp(&a.score).then(a.task.id().cmp(b.task.id())));
+
+        matches
+    }
+
+    fn matches(&self, task: &Task) -> Option<Match> {
+        let (score, ranges) = match self {
+            Self::Fuzzy(words) => {
+                let mut score = 0;
+                let mut ranges = vec![];
+                for word in words {
+                    let (word_score, word_ranges) = match_word(word, task)?;
+                    score += word_score;
+                    ranges.extend(word_ranges);
+                }
+
+                (score, merge(ranges))
+            },
+            Self::Regex(regex) => {
+                // Empty matches, like `x*` finds everywhere, do not count
+                let found = |text| regex.find_iter(text).filter(|m| !m.is_empty());
+                let ranges: Vec<_> = found(task.text()).map(|m| m.range()).collect();
+                let tags = task
+                    .tags()
+                    .iter()
+                    .filter(|t| found(t).next().is_some())
+                    .count();
+                if ranges.is_empty() && tags == 0 {
+                    return None;
+                }
+
+                (ranges.len() as u32 + tags as u32, merge(ranges))
+            },
+        };
+
+        Some(Match {
+            task: task.clone(),
+            score,
+            ranges,
+        })
+    }
+}
+
+impl PartialEq for Search {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Fuzzy(a), Self::Fuzzy(b)) => a == b,
+            (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Search {}
+
+/// A task found by a [Search]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Match {
+    pub task: Task,
+    /// Higher the more relevant
+    pub score: u32,
+    /// Byte ranges of the text that matched, in order
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// Best match of a single query word against the text or any tag of the task.
+/// Words starting with `#` only match tags.
+fn match_word(word: &str, task: &Task) -> Option<(u32, Vec<Range<usize>>)> {
+    let (word, tags_only) = match word.strip_prefix('#') {
+        Some(tag) => (tag, true),
+        None => (word, false),
+    };
+
+    let text = match tags_only {
+        true => None,
+        false => fuzzy_match(word, task.text()),
+    };
+    let tag = task
+        .tags()
+        .iter()
+        .filter_map(|t| fuzzy_match(word, t))
+        .map(|(score, _)| score)
+        .max();
+
+    match (text, tag) {
+        (Some((score, ranges)), Some(tag)) if score >= tag => Some((score, ranges)),
+        (Some(text), None) => Some(text),
+        (_, Some(tag)) => Some((tag, vec![])),
+        (None, None) => None,
+    }
+}
+
+/// Score of the characters of the query found in order in the text, ignoring
+/// case, and the byte ranges they cover.
+/// Every place the first character appears is tried, keeping the best one.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(u32, Vec<Range<usize>>)> {
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let chars: Vec<(Range<usize>, char)> = text
+        .char_indices()
+        .map(|(i, c)| (i..i + c.len_utf8(), fold(c)))
+        .collect();
+    let first = *query.first()?;
+
+    let (score, positions) = (0..chars.len())
+        .filter(|&start| chars[start].1 == first)
+        .filter_map(|start| {
+            let mut positions = vec![start];
+            for (position, (_, c)) in chars.iter().enumerate().skip(start + 1) {
+                if positions.len() < query.len() && *c == query[positions.len()] {
+                    positions.push(position);
+                }
+            }
+
+            (positions.len() == query.len()).then(|| (score(&chars, &positions), positions))
+        })
+        // The earliest one wins a tie
+        .min_by(|a, b| b.0.cmp(&a.0).then(a.1[0].cmp(&b.1[0])))?;
+
+    let ranges = positions.iter().map(|&p| chars[p].0.clone()).collect();
+    Some((score, merge(ranges)))
+}
+
+fn score(chars: &[(Range<usize>, char)], positions: &[usize]) -> u32 {
+    let mut score = 0;
+    for (i, &position) in positions.iter().enumerate() {
+        score += CHAR_SCORE;
+        if i > 0 && positions[i - 1] + 1 == position {
+            score += CONSECUTIVE_BONUS;
+        }
+        if position == 0 || !chars[position - 1].1.is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+    }
+
+    if positions.windows(2).all(|w| w[0] + 1 == w[1]) {
+        score += SUBSTRING_BONUS;
+    }
+
+    score
+}
+
+/// Character compared when ignoring case
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Sorts the ranges, joining the ones that overlap or touch
+fn merge(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.sort_by_key(|r| r.start);
+
+    let mut merged: Vec<Range<usize>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod test {
+    use super::*;
+    use crate::task::Attributes;
+    use crate::task::TaskId;
+
+    fn task(id: usize, text: &str, tag: &str) -> Task {
+        let mut task = Task::new(TaskId::new(id), text);
+        let mut attributes = Attributes::default();
+        attributes.parse_arg(&format!("#{}", tag)).unwrap();
+        task.set_attributes(&attributes);
+
+        task
+    }
+
+    fn ids(matches: &[Match]) -> Vec<usize> {
+        matches.iter().map(|m| m.task.id().val()).collect()
+    }
+
+    #[test]
+    fn it_matches_scattered_characters() {
+        assert_eq!(
+            fuzzy_match("rpt", "Write report"),
+            Some((5, vec![6..7, 8..9, 11..12]))
+        );
+        assert_eq!(fuzzy_match("REP", "Write report"), Some((23, vec![6..9])));
+        assert_eq!(fuzzy_match("rpx", "Write report"), None);
+        assert_eq!(fuzzy_match("é", "Café"), Some((11, vec![3..5])));
+    }
+
+    #[test]
+    fn it_ranks_tasks_by_relevance_and_id() {
+        let tasks = [
+            task(1, "Prepare slides", "work"),
+            task(2, "Write report", "work"),
+            task(3, "Read a paper on reports", "reading"),
+            task(4, "Water the plants", "home"),
+            task(5, "Rewrite report", "work"),
+        ];
+
+        let matches = Search::parse("report").unwrap().apply(tasks.iter());
+        assert_eq!(ids(&matches), vec![2, 3, 5]);
+        assert_eq!(matches[0].ranges, vec![6..12]);
+
+        let matches = Search::parse("rep #work").unwrap().apply(tasks.iter());
+        assert_eq!(ids(&matches), vec![2, 5, 1]);
+
+        let matches = Search::parse("home").unwrap().apply(tasks.iter());
+        assert_eq!(ids(&matches), vec![4]);
+        assert!(matches[0].ranges.is_empty());
+    }
+
+    #[test]
+    fn it_searches_with_regex() {
+        let tasks = [
+            task(1, "Call 555-1234 or 555-9876", "phone"),
+            task(2, "Call 555-0000", "phone"),
+            task(3, "Email Bob", "mail"),
+        ];
+
+        let matches = Search::parse(r"regex:\d{3}-\d{4}")
+            .unwrap()
+            .apply(tasks.iter());
+        assert_eq!(ids(&matches), vec![1, 2]);
+        assert_eq!(matches[0].ranges, vec![5..13, 17..25]);
+
+        let matches = Search::parse("regex:^ma").unwrap().apply(tasks.iter());
+        assert_eq!(ids(&matches), vec![3]);
+
+        let matches = Search::parse("regex:5*").unwrap().apply(tasks.iter());
+        assert_eq!(ids(&matches), vec![1, 2]);
+        assert_eq!(matches[0].ranges, vec![5..8, 17..20]);
+
+        assert_eq!(
+            Search::parse("regex:(unclosed"),
+            Err(String::from("(unclosed"))
+        );
+    }
+}
@@ -0,0 +1,613 @@
+//! Small HTTP API over a [TaskList], so tasks can be managed without the
+//! prompt:
+//!
+//! - `GET /tasks` lists the tasks, `?filter=` taking the same filters as `list`
+//! - `GET /tasks/{id}` shows a single task
+//! - `POST /tasks` creates a task from a JSON object like the ones `export`
+//!   writes, where only `text` is required
+//! - `PATCH /tasks/{id}` changes the text, status or attributes given, all
+//!   of them or none
+//! - `DELETE /tasks/{id}` deletes a task, along with its subtasks when
+//!   `?cascade=true`
+//!
+//! Every request is turned into a [Command] executed on the one shared list,
+//! so requests are applied one at a time whatever the number of connections.
+//! Blockers are left to `block` and `unblock`, so they can not be given.
+
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::json;
+use serde_json::Value;
+
+use crate::command::build_command;
+use crate::command::Command;
+use crate::exchange;
+use crate::printer::Printer;
+use crate::query::ListQuery;
+use crate::task::TaskId;
+use crate::task::TaskStatus;
+use crate::task_list::Outcome;
+use crate::task_list::TaskError;
+use crate::task_list::TaskList;
+
+/// Port used when `--port` is not given
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY_LEN: usize = 1024 * 1024;
+
+/// Largest request line and headers accepted, in bytes
+const MAX_HEAD_LEN: u64 = 8 * 1024;
+
+/// Connections handled at the same time, further ones are turned away
+const MAX_CONNECTIONS: usize = 32;
+
+/// Time a connection may stay silent before it is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Request {
+    method: String,
+    path: String,
+    /// Decoded query string parameters, in order
+    params: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct Response {
+    status: u16,
+    body: Option<Value>,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            body: Some(body),
+        }
+    }
+
+    fn error(status: u16, msg: &str) -> Self {
+        Self::json(status, json!({ "error": msg }))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+
+    fn write_to(&self, stream: &mut impl Write) -> Result<(), io::Error> {
+        let body = match &self.body {
+            Some(b) => b.to_string(),
+            None => String::new(),
+        };
+
+        write!(stream, "HTTP/1.1 {} {}\r\n", self.status, self.reason())?;
+        if self.body.is_some() {
+            write!(stream, "Content-Type: application/json\r\n")?;
+        }
+        write!(
+            stream,
+            "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+
+        stream.flush()
+    }
+}
+
+impl From<TaskError> for Response {
+    fn from(error: TaskError) -> Self {
+        let status = match error {
+            TaskError::UnknownTask(_) => 404,
+            TaskError::HasSubtasks(_)
+            | TaskError::InvalidParent(_)
+            | TaskError::Blocked(..)
+            | TaskError::BlockCycle(..)
+            | TaskError::Conflict(_)
+            | TaskError::NoIdsLeft => 409,
+            TaskError::Io(_) | TaskError::Import(_) | TaskError::Export(_) => 500,
+            _ => 400,
+        };
+
+        Self::error(status, &error.to_string())
+    }
+}
+
+/// Serves a [TaskList] on localhost, each connection being handled by its own
+/// thread, up to [MAX_CONNECTIONS] at the same time
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+    task_list: Arc<Mutex<TaskList>>,
+    printer: Box<Printer>,
+    /// Connections being handled
+    connections: Arc<AtomicUsize>,
+}
+
+impl Server {
+    /// Listens on the given port of the loopback interface, any free one
+    /// when it is 0
+    pub fn bind(printer: Box<Printer>, port: u16, task_list: TaskList) -> Result<Self, io::Error> {
+        Ok(Self {
+            listener: TcpListener::bind((Ipv4Addr::LOCALHOST, port))?,
+            task_list: Arc::new(Mutex::new(task_list)),
+            printer,
+            connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, io::Error> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections until the process is stopped
+    pub fn run(&self) {
+        for stream in self.listener.incoming() {
+            let mut stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    self.printer
+                        .warning(&format!("Unable to accept connection: {}", e));
+                    continue;
+                },
+            };
+
+            if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                self.connections.fetch_sub(1, Ordering::SeqCst);
+                let _ = Response::error(503, "Too many connections").write_to(&mut stream);
+                continue;
+            }
+
+            let task_list = Arc::clone(&self.task_list);
+            let printer = self.printer.clone();
+            let connections = Arc::clone(&self.connections);
+            thread::spawn(move || {
+                if let Err(e) = handle(stream, &task_list) {
+                    printer.warning(&format!("Unable to answer request: {}", e));
+                }
+                connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+}
+
+fn handle(mut stream: TcpStream, task_list: &Mutex<TaskList>) -> Result<(), io::Error> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let response = match read_request(&mut BufReader::new(&stream)) {
+        Ok(request) => match task_list.lock() {
+            Ok(mut task_list) => route(&request, &mut task_list),
+            // A previous request panicked halfway through a change
+            Err(_) => Response::error(500, "Task list unavailable"),
+        },
+        Err(response) => response,
+    };
+
+    response.write_to(&mut stream)
+}
+
+fn read_request(reader: &mut impl BufRead) -> Result<Request, Response> {
+    let bad_request = |_| Response::error(400, "Malformed request");
+
+    let mut head = (&mut *reader).take(MAX_HEAD_LEN);
+    let mut read_line = || {
+        let mut line = String::new();
+        head.read_line(&mut line).map_err(bad_request)?;
+        if !line.ends_with('\n') && head.limit() == 0 {
+            return Err(Response::error(431, "Request head too large"));
+        }
+
+        Ok(line)
+    };
+
+    let line = read_line()?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(m), Some(t), Some(v)) if v.starts_with("HTTP/1.") => (m, t),
+        _ => return Err(Response::error(400, "Malformed request")),
+    };
+
+    let mut content_length = 0;
+    loop {
+        let header = read_line()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Response::error(400, "Invalid Content-Length"))?;
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_LEN {
+        return Err(Response::error(413, "Request body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (name, value) = p.split_once('=').unwrap_or((p, ""));
+            Some((decode(name)?, decode(value)?))
+        })
+        .collect::<Option<_>>()
+        .ok_or(Response::error(400, "Malformed query string"))?;
+
+    Ok(Request {
+        method: String::from(method),
+        path: String::from(path),
+        params,
+        body,
+    })
+}
+
+/// Decodes a percent encoded query string component
+fn decode(input: &str) -> Option<String> {
+    let mut bytes = vec![];
+    let mut chars = input.bytes();
+    while let Some(b) = chars.next() {
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [chars.next()?, chars.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            },
+            b => bytes.push(b),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+fn route(request: &Request, task_list: &mut TaskList) -> Response {
+    let segments: Vec<_> = request.path.trim_matches('/').split('/').collect();
+    let id = match segments.as_slice() {
+        ["tasks"] => None,
+        ["tasks", id] => match id.parse() {
+            Ok(id) => Some(TaskId::new(id)),
+            Err(_) => return Response::error(404, "Not found"),
+        },
+        _ => return Response::error(404, "Not found"),
+    };
+
+    let result = match (request.method.as_str(), id) {
+        ("GET", None) => list(task_list, request.param("filter").unwrap_or("")),
+        ("POST", None) => create(task_list, &request.body),
+        ("GET", Some(id)) => show(task_list, id, 200),
+        ("PATCH", Some(id)) => update(task_list, id, &request.body),
+        ("DELETE", Some(id)) => {
+            let cascade = request.param("cascade") == Some("true");
+            delete(task_list, id, cascade)
+        },
+        _ => Err(Response::error(405, "Method not allowed")),
+    };
+
+    result.unwrap_or_else(|response| response)
+}
+
+fn list(task_list: &mut TaskList, filter: &str) -> Result<Response, Response> {
+    let query = match build_command(&format!("list {}", filter)) {
+        Ok(Command::List(query)) if !query.all_lists => query,
+        Ok(_) => return Err(Response::error(400, "Invalid filter")),
+        Err(e) => return Err(Response::error(400, &e.val())),
+    };
+
+    match task_list.execute(Command::List(query))? {
+        Outcome::Listed(tasks) => Ok(Response::json(200, json!(tasks))),
+        _ => Err(Response::error(500, "Unexpected outcome")),
+    }
+}
+
+fn show(task_list: &mut TaskList, id: TaskId, status: u16) -> Result<Response, Response> {
+    // Listing brings the tasks up to date with their file
+    task_list.execute(Command::List(ListQuery::default()))?;
+
+    match task_list.get(id) {
+        Some(task) => Ok(Response::json(status, json!(task))),
+        None => Err(TaskError::UnknownTask(id).into()),
+    }
+}
+
+/// Reads a JSON object with the fields of a task
+fn read_body(body: &[u8]) -> Result<exchange::TaskFields, Response> {
+    let value: Value = serde_json::from_slice(body)
+        .map_err(|e| Response::error(400, &format!("Invalid JSON: {}", e)))?;
+    if !value.is_object() {
+        return Err(Response::error(400, "Expected a JSON object"));
+    }
+
+    exchange::read_fields(&value).map_err(|e| Response::error(400, &format!("Invalid task: {}", e)))
+}
+
+/// Tasks are only blocked through `block`, never by setting their status or
+/// blockers
+fn check_blocking(fields: &exchange::TaskFields) -> Result<(), Response> {
+    if fields.status == Some(TaskStatus::Blocked) {
+        return Err(Response::error(400, "Invalid task: invalid status"));
+    }
+    if !fields.blockers.is_empty() {
+        return Err(Response::error(
+            400,
+            "Invalid task: blockers can not be set",
+        ));
+    }
+
+    Ok(())
+}
+
+fn create(task_list: &mut TaskList, body: &[u8]) -> Result<Response, Response> {
+    let fields = read_body(body)?;
+    check_blocking(&fields)?;
+    let text = match fields.text {
+        Some(t) if !t.trim().is_empty() => t,
+        _ => return Err(Response::error(400, "Invalid task: missing text")),
+    };
+
+    let mut add = Some(Command::Add(text, fields.attributes));
+    let done = fields.status == Some(TaskStatus::Done);
+    let outcomes = task_list.execute_all(|outcomes| match outcomes {
+        [] => add.take(),
+        [Outcome::Created(id)] if done => Some(Command::Do((*id).into())),
+        _ => None,
+    })?;
+
+    match outcomes.first() {
+        Some(Outcome::Created(id)) => show(task_list, *id, 201),
+        _ => Err(Response::error(500, "Unexpected outcome")),
+    }
+}
+
+fn update(task_list: &mut TaskList, id: TaskId, body: &[u8]) -> Result<Response, Response> {
+    let fields = read_body(body)?;
+    check_blocking(&fields)?;
+
+    let mut commands = vec![];
+    if fields.text.is_some() || !fields.attributes.is_empty() {
+        commands.push(Command::Edit(id, fields.text, fields.attributes));
+    }
+    match fields.status {
+        Some(TaskStatus::Done) => commands.push(Command::Do(id.into())),
+        Some(TaskStatus::Pending) => commands.push(Command::UnDo(id.into())),
+        Some(TaskStatus::Blocked) | None => (),
+    };
+    let mut commands = commands.into_iter();
+    task_list.execute_all(|_| commands.next())?;
+
+    show(task_list, id, 200)
+}
+
+fn delete(task_list: &mut TaskList, id: TaskId, cascade: bool) -> Result<Response, Response> {
+    task_list.execute(Command::Delete(id.into(), cascade))?;
+
+    Ok(Response {
+        status: 204,
+        body: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    fn serve() -> SocketAddr {
+        serve_list(TaskList::with_store(Box::default(), Box::new(MemoryStore::new())).unwrap())
+    }
+
+    fn serve_list(task_list: TaskList) -> SocketAddr {
+        let server = Server::bind(Box::default(), 0, task_list).unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        addr
+    }
+
+    /// Status and body of the response
+    fn request(addr: SocketAddr, method: &str, target: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            target,
+            body.len(),
+            body
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head[9..12].parse().unwrap();
+        let body = serde_json::from_str(body).unwrap_or(Value::Null);
+
+        (status, body)
+    }
+
+    #[test]
+    fn it_manages_tasks_over_http() {
+        let addr = serve();
+
+        let body = r#"{"text": "Write report", "priority": "high", "tags": ["work"]}"#;
+        let (status, task) = request(addr, "POST", "/tasks", body);
+        assert_eq!(status, 201);
+        assert_eq!(task["id"], 1);
+        assert_eq!(task["priority"], "high");
+        request(addr, "POST", "/tasks", r#"{"text": "Buy milk"}"#);
+
+        let (status, task) = request(addr, "PATCH", "/tasks/1", r#"{"status": "done"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(task["status"], "done");
+        assert_eq!(task["text"], "Write report");
+
+        let (status, tasks) = request(addr, "GET", "/tasks?filter=pending", "");
+        assert_eq!(status, 200);
+        assert_eq!(tasks.as_array().unwrap().len(), 1);
+        assert_eq!(tasks[0]["text"], "Buy milk");
+
+        let (status, _) = request(addr, "DELETE", "/tasks/2", "");
+        assert_eq!(status, 204);
+        let (status, body) = request(addr, "GET", "/tasks/2", "");
+        assert_eq!(status, 404);
+        assert_eq!(body["error"], "Unknown task with key 2");
+    }
+
+    #[test]
+    fn it_rejects_invalid_requests() {
+        let addr = serve();
+
+        let (status, body) = request(addr, "POST", "/tasks", r#"{"text": "Run", "due": "soon"}"#);
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "Invalid task: invalid due");
+        assert_eq!(request(addr, "POST", "/tasks", "[1, 2]").0, 400);
+        assert_eq!(request(addr, "POST", "/tasks", "{}").0, 400);
+        assert_eq!(request(addr, "PATCH", "/tasks/7", "{}").0, 404);
+        assert_eq!(request(addr, "PUT", "/tasks/7", "{}").0, 405);
+        assert_eq!(request(addr, "GET", "/projects", "").0, 404);
+        assert_eq!(request(addr, "GET", "/tasks?filter=due%3Asoon", "").0, 400);
+    }
+
+    #[test]
+    fn it_applies_every_change_of_a_patch_or_none() {
+        let mut task_list =
+            TaskList::with_store(Box::default(), Box::new(MemoryStore::new())).unwrap();
+        for text in ["Write report", "Get numbers"] {
+            task_list
+                .execute(Command::Add(String::from(text), Default::default()))
+                .unwrap();
+        }
+        task_list
+            .execute(Command::Block(TaskId::new(1), TaskId::new(2)))
+            .unwrap();
+        let addr = serve_list(task_list);
+
+        let body = r#"{"text": "Renamed", "status": "done"}"#;
+        let (status, body) = request(addr, "PATCH", "/tasks/1", body);
+        assert_eq!(status, 409);
+        assert_eq!(
+            body["error"],
+            "Task with key 1 is blocked on pending task(s) 2"
+        );
+        assert_eq!(
+            request(addr, "GET", "/tasks/1", "").1["text"],
+            "Write report"
+        );
+
+        let (status, body) = request(addr, "PATCH", "/tasks/2", r#"{"blockers": [1]}"#);
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "Invalid task: blockers can not be set");
+        let body = r#"{"text": "Wait", "blockers": [2]}"#;
+        assert_eq!(request(addr, "POST", "/tasks", body).0, 400);
+    }
+
+    #[test]
+    fn it_creates_done_tasks_as_a_single_change() {
+        let mut task_list =
+            TaskList::with_store(Box::default(), Box::new(MemoryStore::new())).unwrap();
+
+        let body = br#"{"text": "Pay rent", "status": "done"}"#;
+        assert_eq!(create(&mut task_list, body).unwrap().status, 201);
+        assert_eq!(
+            task_list.query(&ListQuery::default())[0].status(),
+            &TaskStatus::Done
+        );
+
+        task_list.execute(Command::Revert).unwrap();
+        assert!(task_list.query(&ListQuery::default()).is_empty());
+    }
+
+    #[test]
+    fn it_rejects_request_heads_too_large() {
+        let addr = serve();
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut head = String::from("GET /tasks HTTP/1.1\r\nX-Padding: ");
+        head.extend(std::iter::repeat_n('x', MAX_HEAD_LEN as usize - head.len()));
+        stream.write_all(head.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 431 "));
+    }
+
+    #[test]
+    fn it_shares_the_list_between_connections() {
+        let addr = serve();
+
+        let clients: Vec<_> = (0..8)
+            .map(|i| {
+                thread::spawn(move || {
+                    let body = format!(r#"{{"text": "Task {}"}}"#, i);
+                    request(addr, "POST", "/tasks", &body).0
+                })
+            })
+            .collect();
+        for client in clients {
+            assert_eq!(client.join().unwrap(), 201);
+        }
+
+        let (_, tasks) = request(addr, "GET", "/tasks", "");
+        let mut ids: Vec<_> = tasks
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["id"].as_u64().unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, (1..=8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn it_decodes_query_strings() {
+        assert_eq!(
+            decode("%23work+due%3Atoday"),
+            Some(String::from("#work due:today"))
+        );
+        assert_eq!(decode("100%"), None);
+    }
+}
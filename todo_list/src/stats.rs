@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use crate::date::Date;
+use crate::task::Task;
+
+/// Completion figures of a set of tasks.
+/// Tasks without a completion time, like the ones completed before timestamps
+/// existed, are left out.
+#[derive(PartialEq, Eq, Debug, Default)]
+pub struct Stats {
+    /// Completed tasks per day
+    pub per_day: BTreeMap<Date, usize>,
+    /// Completed tasks per week, keyed by the Monday it starts on
+    pub per_week: BTreeMap<Date, usize>,
+    /// Average seconds between creating and completing a task
+    pub average_time_to_finish: Option<u64>,
+}
+
+impl Stats {
+    pub fn compute<'a>(tasks: impl Iterator<Item = &'a Task>) -> Self {
+        let mut stats = Self::default();
+        let mut finish_times = vec![];
+
+        for task in tasks {
+            let completed = match task.completed() {
+                Some(c) => c,
+                None => continue,
+            };
+
+            let day = completed.date();
+            let monday = day.add_days(-(day.weekday() as i64));
+            *stats.per_day.entry(day).or_default() += 1;
+            *stats.per_week.entry(monday).or_default() += 1;
+
+            if let Some(created) = task.created() {
+                finish_times.push(completed.seconds().saturating_sub(created.seconds()));
+            }
+        }
+
+        if !finish_times.is_empty() {
+            stats.average_time_to_finish =
+                Some(finish_times.iter().sum::<u64>() / finish_times.len() as u64);
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::date::Timestamp;
+    use crate::task::TaskId;
+
+    fn task(id: usize, created: u64, completed: Option<u64>) -> Task {
+        let mut task = Task::new(TaskId::new(id), "Task");
+        task.set_created(Timestamp::new(created));
+        if let Some(completed) = completed {
+            task.r#do();
+            task.touch(Timestamp::new(completed));
+        }
+
+        task
+    }
+
+    #[test]
+    fn it_counts_completed_tasks_per_day_and_week() {
+        // 2026-10-16 is a Friday
+        let friday = 1_792_108_800;
+        let day = 86_400;
+        let tasks = [
+            task(1, friday - day, Some(friday)),
+            task(2, friday, Some(friday + 3_600)),
+            task(3, friday, Some(friday + 3 * day)),
+            task(4, friday, None),
+        ];
+
+        let stats = Stats::compute(tasks.iter());
+        let date = |d| Date::new(2026, 10, d).unwrap();
+        assert_eq!(
+            stats.per_day,
+            BTreeMap::from([(date(16), 2), (date(19), 1)])
+        );
+        assert_eq!(
+            stats.per_week,
+            BTreeMap::from([(date(12), 2), (date(19), 1)])
+        );
+        assert_eq!(
+            stats.average_time_to_finish,
+            Some((day + 3_600 + 3 * day) / 3)
+        );
+    }
+
+    #[test]
+    fn it_has_no_average_without_completed_tasks() {
+        let stats = Stats::compute([task(1, 0, None)].iter());
+        assert_eq!(stats, Stats::default());
+    }
+}
@@ -0,0 +1,93 @@
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use crate::task::Task;
+use crate::task::TaskId;
+
+pub mod csv;
+pub mod memory;
+
+pub use self::csv::CsvStore;
+pub use self::memory::MemoryStore;
+
+/// Result of loading a [TaskStore].
+/// Entries that could not be read are reported as `warnings` instead of
+/// failing the whole load.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub tasks: Vec<Task>,
+    /// Id the next created task gets, if it was stored
+    pub next_id: Option<TaskId>,
+    pub warnings: Vec<String>,
+}
+
+/// Persistence backend used by a [TaskList](crate::task_list::TaskList)
+pub trait TaskStore: fmt::Debug + Send {
+    /// Reads every stored task
+    fn load(&mut self) -> Result<Snapshot, io::Error>;
+
+    /// Replaces the whole stored content with the given tasks and the id the
+    /// next created task gets
+    fn save(&mut self, tasks: &[&Task], next_id: TaskId) -> Result<(), io::Error>;
+
+    /// Stores a single new task without rewriting the existing ones
+    fn append(&mut self, task: &Task) -> Result<(), io::Error>;
+
+    /// Blocks until this store holds an exclusive lock shared with any other
+    /// process using the same backend
+    fn lock(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn unlock(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    /// Whether someone else changed the stored content since this store last
+    /// loaded or wrote it
+    fn is_modified(&mut self) -> Result<bool, io::Error> {
+        Ok(false)
+    }
+}
+
+/// Replaces `file` with `content` without ever leaving it half written.
+///
+/// The content is written and synced to a temporary file next to the target,
+/// which is then renamed over it.
+pub fn write_atomically(file: &str, content: &[u8]) -> Result<(), io::Error> {
+    let target = Path::new(file);
+    let tmp = target.with_file_name(format!(
+        ".{}.tmp",
+        target
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("tasks")
+    ));
+
+    let mut tmp_file = File::create(&tmp)?;
+    if let Err(e) = tmp_file
+        .write_all(content)
+        .and_then(|_| tmp_file.sync_all())
+    {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    drop(tmp_file);
+
+    fs::rename(&tmp, target)?;
+
+    // Persist the rename itself. Not every platform allows opening directories.
+    let dir = match target.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
@@ -0,0 +1,372 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path;
+
+use crate::csv;
+use crate::store;
+use crate::store::Snapshot;
+use crate::store::TaskStore;
+use crate::task::Task;
+use crate::task::TaskId;
+
+/// [TaskStore] backed by a semicolon separated file with one task per record.
+/// The first record holds the id the next created task gets, as in
+/// `next_id;8`.
+///
+/// Locking uses an advisory lock on a `.lock` file next to it, since saving
+/// replaces the task file itself.
+#[derive(Debug)]
+pub struct CsvStore {
+    file: String,
+    lock: Option<File>,
+    fingerprint: Option<u64>,
+    /// Records of the last load that could not be read, saved back as they
+    /// were so they are not lost
+    rejected: Vec<String>,
+}
+
+impl CsvStore {
+    pub fn new(file: &str) -> Self {
+        Self {
+            file: String::from(file),
+            lock: None,
+            fingerprint: None,
+            rejected: vec![],
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Whether the file starts with the next id record every save writes,
+    /// which tells task files apart from any other CSV file
+    pub fn is_task_file(file: &path::Path) -> bool {
+        let mut first = String::new();
+        match File::open(file).map(|f| BufReader::new(f).read_line(&mut first)) {
+            Ok(Ok(_)) => first.starts_with(&format!("{}{}", NEXT_ID_FIELD, csv::SEPARATOR)),
+            _ => false,
+        }
+    }
+
+    fn current_fingerprint(&self) -> Result<Option<u64>, io::Error> {
+        match fs::read(&self.file) {
+            Ok(content) => {
+                let mut hasher = DefaultHasher::new();
+                content.hash(&mut hasher);
+                Ok(Some(hasher.finish()))
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// First field of the record holding the next task id
+const NEXT_ID_FIELD: &str = "next_id";
+
+fn next_id_record(next_id: TaskId) -> String {
+    csv::encode_record(&[NEXT_ID_FIELD, &next_id.val().to_string()]) + "\n"
+}
+
+fn parse_next_id(record: &csv::Record) -> Option<Result<TaskId, String>> {
+    match record.fields.as_slice() {
+        [name, id] if name == NEXT_ID_FIELD => Some(
+            id.parse()
+                .map(TaskId::new)
+                .map_err(|_| format!("Ignoring next id '{}' due to invalid id", record.raw)),
+        ),
+        _ => None,
+    }
+}
+
+/// Reads the task of a record, or the warning to give about it
+fn parse_record(record: &csv::Record) -> Result<Task, String> {
+    Task::from_csv(&record.fields)
+        .map_err(|reason| format!("Ignoring task '{}' due to {}", record.raw, reason))
+}
+
+impl TaskStore for CsvStore {
+    fn load(&mut self) -> Result<Snapshot, io::Error> {
+        let mut snapshot = Snapshot::default();
+        self.rejected.clear();
+
+        if !path::Path::new(&self.file).exists() {
+            self.fingerprint = None;
+            return Ok(snapshot);
+        }
+
+        let content = fs::read_to_string(&self.file)?;
+        self.fingerprint = self.current_fingerprint()?;
+
+        for record in csv::parse(&content) {
+            let warning = match parse_next_id(&record) {
+                Some(Ok(id)) => {
+                    snapshot.next_id = Some(id);
+                    continue;
+                },
+                Some(Err(warning)) => warning,
+                // Records with a quote never closed are kept too, since texts
+                // written before fields were quoted may start with one
+                None => match parse_record(&record) {
+                    Ok(task) => {
+                        snapshot.tasks.push(task);
+                        continue;
+                    },
+                    Err(warning) => warning,
+                },
+            };
+
+            snapshot.warnings.push(warning);
+            self.rejected.push(record.raw);
+        }
+
+        Ok(snapshot)
+    }
+
+    fn save(&mut self, tasks: &[&Task], next_id: TaskId) -> Result<(), io::Error> {
+        let mut content = next_id_record(next_id);
+        for task in tasks {
+            content += format!("{}\n", task.to_csv()).as_str();
+        }
+        for raw in &self.rejected {
+            content += format!("{}\n", raw).as_str();
+        }
+
+        store::write_atomically(&self.file, content.as_bytes())?;
+        self.fingerprint = self.current_fingerprint()?;
+
+        Ok(())
+    }
+
+    fn append(&mut self, task: &Task) -> Result<(), io::Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)?;
+
+        // A new file starts with the next id too, so it is told apart as a
+        // task file
+        let mut content = String::new();
+        if file.metadata()?.len() == 0 {
+            content += &next_id_record(TaskId::new(task.id().val() + 1));
+        }
+        content += &format!("{}\n", task.to_csv());
+        file.write_all(content.as_bytes())?;
+        file.sync_data()?;
+        self.fingerprint = self.current_fingerprint()?;
+
+        Ok(())
+    }
+
+    fn lock(&mut self) -> Result<(), io::Error> {
+        if self.lock.is_some() {
+            return Ok(());
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(format!("{}.lock", self.file))?;
+        file.lock()?;
+        self.lock = Some(file);
+
+        Ok(())
+    }
+
+    fn unlock(&mut self) -> Result<(), io::Error> {
+        match self.lock.take() {
+            Some(file) => file.unlock(),
+            None => Ok(()),
+        }
+    }
+
+    fn is_modified(&mut self) -> Result<bool, io::Error> {
+        Ok(self.current_fingerprint()? != self.fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::*;
+    use crate::task::Attributes;
+    use crate::task::TaskId;
+    use crate::task::TaskStatus;
+
+    fn temp_file(name: &str) -> String {
+        let path = env::temp_dir().join(format!("todo_list_{}_{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        String::from(path.to_str().unwrap())
+    }
+
+    #[test]
+    fn it_loads_nothing_if_file_does_not_exist() {
+        let mut store = CsvStore::new(&temp_file("missing.csv"));
+        let snapshot = store.load().unwrap();
+        assert!(snapshot.tasks.is_empty());
+        assert!(snapshot.warnings.is_empty());
+    }
+
+    #[test]
+    fn it_saves_appends_and_loads_tasks() {
+        let file = temp_file("save.csv");
+        let mut store = CsvStore::new(&file);
+
+        let first = Task::new(TaskId::new(1), "First");
+        let mut second = Task::new(TaskId::new(2), "Second");
+        second.r#do();
+        store.save(&[&first, &second], TaskId::new(5)).unwrap();
+        store.append(&Task::new(TaskId::new(3), "Third")).unwrap();
+
+        let snapshot = store.load().unwrap();
+        assert_eq!(snapshot.tasks.len(), 3);
+        assert_eq!(snapshot.tasks[1].text(), "Second");
+        assert_eq!(snapshot.tasks[1].status(), &TaskStatus::Done);
+        assert_eq!(snapshot.tasks[2].id(), &TaskId::new(3));
+        assert_eq!(snapshot.next_id, Some(TaskId::new(5)));
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_starts_new_files_with_the_next_id() {
+        let file = temp_file("appended.csv");
+        let mut store = CsvStore::new(&file);
+
+        store.append(&Task::new(TaskId::new(4), "First")).unwrap();
+        store.append(&Task::new(TaskId::new(5), "Second")).unwrap();
+        assert!(CsvStore::is_task_file(path::Path::new(&file)));
+
+        let snapshot = store.load().unwrap();
+        assert_eq!(snapshot.tasks.len(), 2);
+        assert_eq!(snapshot.next_id, Some(TaskId::new(5)));
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_keeps_separators_quotes_and_new_lines_in_text() {
+        let file = temp_file("escaped.csv");
+        let mut store = CsvStore::new(&file);
+
+        let text = "Buy; \"milk\"\nand eggs";
+        store
+            .save(&[&Task::new(TaskId::new(1), text)], TaskId::new(2))
+            .unwrap();
+
+        let snapshot = store.load().unwrap();
+        assert!(snapshot.warnings.is_empty());
+        assert_eq!(snapshot.tasks[0].text(), text);
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_keeps_task_attributes() {
+        let file = temp_file("attributes.csv");
+        let mut store = CsvStore::new(&file);
+
+        let mut attributes = Attributes::default();
+        for arg in ["due:2026-11-01", "prio:urgent", "#release", "#v2", "parent:4"] {
+            attributes.parse_arg(arg).unwrap();
+        }
+        let mut task = Task::new(TaskId::new(1), "Ship release");
+        task.set_attributes(&attributes);
+        store.save(&[&task], TaskId::new(2)).unwrap();
+
+        let snapshot = store.load().unwrap();
+        assert_eq!(snapshot.tasks, vec![task]);
+        assert_eq!(snapshot.tasks[0].attributes(), attributes);
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_detects_external_modifications() {
+        let file = temp_file("modified.csv");
+        let mut store = CsvStore::new(&file);
+        store.load().unwrap();
+        assert!(!store.is_modified().unwrap());
+
+        store.append(&Task::new(TaskId::new(1), "Mine")).unwrap();
+        assert!(!store.is_modified().unwrap());
+
+        let mut other = CsvStore::new(&file);
+        other.lock().unwrap();
+        other.append(&Task::new(TaskId::new(2), "Theirs")).unwrap();
+        other.unlock().unwrap();
+        assert!(store.is_modified().unwrap());
+
+        store.lock().unwrap();
+        assert_eq!(store.load().unwrap().tasks.len(), 2);
+        assert!(!store.is_modified().unwrap());
+        store.unlock().unwrap();
+
+        fs::remove_file(format!("{}.lock", file)).unwrap();
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_loads_old_texts_starting_with_a_quote() {
+        let file = temp_file("old_quote.csv");
+        fs::write(&file, "1;pending;\"foo bar\n2;done;Next\n").unwrap();
+
+        let snapshot = CsvStore::new(&file).load().unwrap();
+        assert!(snapshot.warnings.is_empty());
+        assert_eq!(snapshot.tasks.len(), 2);
+        assert_eq!(snapshot.tasks[0].text(), "\"foo bar");
+        assert_eq!(snapshot.tasks[1].text(), "Next");
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_warns_about_malformed_lines() {
+        let file = temp_file("malformed.csv");
+        fs::write(
+            &file,
+            "next_id;x\n1;pending;Ok\nnot a task\nx;done;Bad id\n2;maybe;Bad status\n",
+        )
+        .unwrap();
+
+        let snapshot = CsvStore::new(&file).load().unwrap();
+        assert_eq!(snapshot.tasks.len(), 1);
+        assert_eq!(snapshot.next_id, None);
+        assert_eq!(snapshot.warnings.len(), 4);
+        assert!(snapshot.warnings[0].starts_with("Ignoring next id"));
+        assert!(snapshot.warnings[1].ends_with("due to missmatched parts"));
+        assert!(snapshot.warnings[2].ends_with("due to invalid id"));
+        assert!(snapshot.warnings[3].ends_with("due to invalid status"));
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_saves_malformed_lines_back() {
+        let file = temp_file("kept.csv");
+        fs::write(&file, "1;pending;Ok\nnot a task\n").unwrap();
+
+        let mut store = CsvStore::new(&file);
+        let snapshot = store.load().unwrap();
+        let tasks: Vec<_> = snapshot.tasks.iter().collect();
+        store.save(&tasks, TaskId::new(2)).unwrap();
+
+        assert!(fs::read_to_string(&file)
+            .unwrap()
+            .ends_with("\nnot a task\n"));
+        assert_eq!(store.load().unwrap().warnings.len(), 1);
+
+        fs::remove_file(file).unwrap();
+    }
+}
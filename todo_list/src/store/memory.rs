@@ -0,0 +1,52 @@
+use std::io;
+
+use crate::store::Snapshot;
+use crate::store::TaskStore;
+use crate::task::Task;
+use crate::task::TaskId;
+
+/// [TaskStore] that keeps the tasks in memory.
+/// Nothing is persisted once it is dropped, which makes it handy for tests.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    tasks: Vec<Task>,
+    next_id: Option<TaskId>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tasks(tasks: Vec<Task>) -> Self {
+        Self {
+            tasks,
+            next_id: None,
+        }
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+}
+
+impl TaskStore for MemoryStore {
+    fn load(&mut self) -> Result<Snapshot, io::Error> {
+        Ok(Snapshot {
+            tasks: self.tasks.clone(),
+            next_id: self.next_id,
+            warnings: vec![],
+        })
+    }
+
+    fn save(&mut self, tasks: &[&Task], next_id: TaskId) -> Result<(), io::Error> {
+        self.tasks = tasks.iter().map(|t| (*t).clone()).collect();
+        self.next_id = Some(next_id);
+        Ok(())
+    }
+
+    fn append(&mut self, task: &Task) -> Result<(), io::Error> {
+        self.tasks.push(task.clone());
+        Ok(())
+    }
+}
@@ -1,10 +1,21 @@
+use std::collections::BTreeSet;
 use std::fmt;
 use std::hash::Hash;
 
-#[derive(Debug)]
+use serde::Serialize;
+
+use crate::csv;
+use crate::date::Date;
+use crate::date::Timestamp;
+
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TaskStatus {
     Pending,
     Done,
+    /// Pending until every task it is blocked on is done, see
+    /// [Task::blockers]
+    Blocked,
 }
 
 impl TaskStatus {
@@ -12,14 +23,368 @@ impl TaskStatus {
         match self {
             Self::Pending => String::from("pending"),
             Self::Done => String::from("done"),
+            Self::Blocked => String::from("blocked"),
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input {
+            "pending" => Some(Self::Pending),
+            "done" => Some(Self::Done),
+            "blocked" => Some(Self::Blocked),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    pub fn val(&self) -> String {
+        match self {
+            Self::Low => String::from("low"),
+            Self::Normal => String::from("normal"),
+            Self::High => String::from("high"),
+            Self::Urgent => String::from("urgent"),
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "normal" => Some(Self::Normal),
+            "high" => Some(Self::High),
+            "urgent" => Some(Self::Urgent),
+            _ => None,
+        }
+    }
+}
+
+/// How often a task is due again once done
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence {
+    Daily,
+    /// Every day from Monday to Friday
+    Weekday,
+    Weekly,
+    Monthly,
+}
+
+impl Recurrence {
+    pub fn val(&self) -> String {
+        match self {
+            Self::Daily => String::from("daily"),
+            Self::Weekday => String::from("weekday"),
+            Self::Weekly => String::from("weekly"),
+            Self::Monthly => String::from("monthly"),
+        }
+    }
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "daily" => Some(Self::Daily),
+            "weekday" | "weekdays" => Some(Self::Weekday),
+            "weekly" => Some(Self::Weekly),
+            "monthly" => Some(Self::Monthly),
+            _ => None,
+        }
+    }
+
+    /// The due date following the given one
+    pub fn next(&self, due: Date) -> Date {
+        match self {
+            Self::Daily => due.add_days(1),
+            Self::Weekday => match due.weekday() {
+                4 => due.add_days(3),
+                5 => due.add_days(2),
+                _ => due.add_days(1),
+            },
+            Self::Weekly => due.add_days(7),
+            Self::Monthly => due.add_months(1),
+        }
+    }
+}
+
+/// Attribute removed from a task by an edit, written as `due:none`,
+/// `prio:none`, `-#tag`, `parent:none` or `every:none`
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum Removal {
+    Due,
+    Priority,
+    Tag(String),
+    Parent,
+    Recurrence,
+}
+
+impl Removal {
+    pub fn val(&self) -> String {
+        match self {
+            Self::Due => String::from("due:none"),
+            Self::Priority => String::from("prio:none"),
+            Self::Tag(tag) => format!("-#{}", tag),
+            Self::Parent => String::from("parent:none"),
+            Self::Recurrence => String::from("every:none"),
+        }
+    }
+}
+
+/// Value of an attribute argument that removes it
+const NONE: &str = "none";
+
+/// Optional task attributes given along with its text, like
+/// `due:2026-11-01 prio:high #release parent:5 every:weekly`
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Attributes {
+    pub due: Option<Date>,
+    pub priority: Option<Priority>,
+    pub tags: BTreeSet<String>,
+    pub parent: Option<TaskId>,
+    pub recurrence: Option<Recurrence>,
+    /// Attributes to remove from the task, never set at the same time
+    pub removed: BTreeSet<Removal>,
+}
+
+impl Attributes {
+    /// Reads a single `due:DATE`, `prio:PRIORITY`, `#tag`, `parent:ID` or
+    /// `every:RECURRENCE` argument, or one removing the attribute like
+    /// `due:none` or `-#tag`.
+    /// It returns `Ok(false)` if the argument is not an attribute at all.
+    pub fn parse_arg(&mut self, arg: &str) -> Result<bool, String> {
+        if let Some(due) = arg.strip_prefix("due:") {
+            self.due = match due {
+                NONE => None,
+                due => Some(Date::parse(due).ok_or(format!("{} (expected YYYY-MM-DD)", arg))?),
+            };
+            self.mark_removed(Removal::Due, self.due.is_none());
+        } else if let Some(priority) = arg.strip_prefix("prio:") {
+            self.priority = match priority {
+                NONE => None,
+                priority => Some(
+                    Priority::parse(priority)
+                        .ok_or(format!("{} (expected low, normal, high or urgent)", arg))?,
+                ),
+            };
+            self.mark_removed(Removal::Priority, self.priority.is_none());
+        } else if let Some(tag) = arg.strip_prefix("-#") {
+            let tag = parse_tag(arg, tag)?;
+            self.tags.remove(&tag);
+            self.mark_removed(Removal::Tag(tag), true);
+        } else if let Some(tag) = arg.strip_prefix('#') {
+            let tag = parse_tag(arg, tag)?;
+            self.tags.insert(tag.clone());
+            self.mark_removed(Removal::Tag(tag), false);
+        } else if let Some(parent) = arg.strip_prefix("parent:") {
+            self.parent = match parent {
+                NONE => None,
+                parent => Some(TaskId::new(
+                    parent
+                        .parse()
+                        .map_err(|_| format!("{} (expected a task id)", arg))?,
+                )),
+            };
+            self.mark_removed(Removal::Parent, self.parent.is_none());
+        } else if let Some(recurrence) = arg.strip_prefix("every:") {
+            self.recurrence = match recurrence {
+                NONE => None,
+                recurrence => Some(Recurrence::parse(recurrence).ok_or(format!(
+                    "{} (expected daily, weekday, weekly or monthly)",
+                    arg
+                ))?),
+            };
+            self.mark_removed(Removal::Recurrence, self.recurrence.is_none());
+        } else {
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// The last argument about an attribute wins, whether it sets or removes it
+    fn mark_removed(&mut self, removal: Removal, removed: bool) {
+        if removed {
+            self.removed.insert(removal);
+        } else {
+            self.removed.remove(&removal);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.due.is_none()
+            && self.priority.is_none()
+            && self.tags.is_empty()
+            && self.parent.is_none()
+            && self.recurrence.is_none()
+            && self.removed.is_empty()
+    }
+
+    /// Fields written to files, see [Attributes::from_fields]
+    pub fn to_fields(&self) -> Vec<String> {
+        let removed: Vec<_> = self.removed.iter().map(|r| r.val()).collect();
+
+        vec![
+            self.due.map_or(String::new(), |d| d.to_string()),
+            self.priority.map_or(String::new(), |p| p.val()),
+            tags_to_field(&self.tags),
+            self.parent.map_or(String::new(), |p| p.val().to_string()),
+            self.recurrence.map_or(String::new(), |r| r.val()),
+            removed.join(" "),
+        ]
+    }
+
+    /// Files written before subtasks, recurring tasks or removals existed
+    /// lack the last fields
+    pub fn from_fields(fields: &[String]) -> Result<Self, String> {
+        if !(3..=6).contains(&fields.len()) {
+            return Err(String::from("missmatched parts"));
         }
+
+        Ok(Self {
+            due: parse_due_field(&fields[0])?,
+            priority: match fields[1].as_str() {
+                "" => None,
+                p => Some(Priority::parse(p).ok_or(String::from("invalid priority"))?),
+            },
+            tags: tags_from_field(&fields[2]),
+            parent: match fields.get(3) {
+                Some(field) => parse_parent_field(field)?,
+                None => None,
+            },
+            recurrence: match fields.get(4) {
+                Some(field) => parse_recurrence_field(field)?,
+                None => None,
+            },
+            removed: match fields.get(5) {
+                Some(field) => parse_removed_field(field)?,
+                None => BTreeSet::new(),
+            },
+        })
     }
 }
 
-#[derive(Eq, Debug, Clone, Copy)]
+impl fmt::Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pieces = vec![];
+        if let Some(due) = self.due {
+            pieces.push(format!("due:{}", due));
+        }
+        if let Some(priority) = self.priority {
+            pieces.push(format!("prio:{}", priority.val()));
+        }
+        for tag in &self.tags {
+            pieces.push(format!("#{}", tag));
+        }
+        if let Some(parent) = self.parent {
+            pieces.push(format!("parent:{}", parent));
+        }
+        if let Some(recurrence) = self.recurrence {
+            pieces.push(format!("every:{}", recurrence.val()));
+        }
+        pieces.extend(self.removed.iter().map(|r| r.val()));
+
+        write!(f, "{}", pieces.join(" "))
+    }
+}
+
+fn parse_tag(arg: &str, tag: &str) -> Result<String, String> {
+    if tag.is_empty() {
+        return Err(format!("{} (empty tag)", arg));
+    }
+    // Tags are stored separated by spaces
+    if tag.contains(char::is_whitespace) {
+        return Err(format!("{} (tags can not contain spaces)", arg));
+    }
+
+    Ok(String::from(tag))
+}
+
+fn tags_to_field(tags: &BTreeSet<String>) -> String {
+    tags.iter().cloned().collect::<Vec<_>>().join(" ")
+}
+
+fn tags_from_field(field: &str) -> BTreeSet<String> {
+    field.split_whitespace().map(String::from).collect()
+}
+
+fn parse_parent_field(field: &str) -> Result<Option<TaskId>, String> {
+    match field {
+        "" => Ok(None),
+        p => Ok(Some(TaskId::new(
+            p.parse().map_err(|_| String::from("invalid parent"))?,
+        ))),
+    }
+}
+
+fn parse_removed_field(field: &str) -> Result<BTreeSet<Removal>, String> {
+    let mut attributes = Attributes::default();
+    for arg in field.split_whitespace() {
+        if attributes.parse_arg(arg) != Ok(true) || attributes.removed.is_empty() {
+            return Err(String::from("invalid removals"));
+        }
+    }
+
+    Ok(attributes.removed)
+}
+
+fn parse_recurrence_field(field: &str) -> Result<Option<Recurrence>, String> {
+    match field {
+        "" => Ok(None),
+        r => Ok(Some(
+            Recurrence::parse(r).ok_or(String::from("invalid recurrence"))?,
+        )),
+    }
+}
+
+fn ids_to_field(ids: &BTreeSet<TaskId>) -> String {
+    let ids: Vec<_> = ids.iter().map(|id| id.val().to_string()).collect();
+    ids.join(" ")
+}
+
+fn parse_ids_field(field: &str) -> Result<BTreeSet<TaskId>, String> {
+    field
+        .split_whitespace()
+        .map(|id| id.parse().map(TaskId::new))
+        .collect::<Result<_, _>>()
+        .map_err(|_| String::from("invalid blockers"))
+}
+
+fn timestamp_to_field(timestamp: Option<Timestamp>) -> String {
+    timestamp.map_or(String::new(), |t| t.seconds().to_string())
+}
+
+fn parse_timestamp_field(field: &str) -> Result<Option<Timestamp>, String> {
+    match field {
+        "" => Ok(None),
+        t => Ok(Some(Timestamp::new(
+            t.parse().map_err(|_| String::from("invalid timestamp"))?,
+        ))),
+    }
+}
+
+fn parse_due_field(field: &str) -> Result<Option<Date>, String> {
+    match field {
+        "" => Ok(None),
+        d => Ok(Some(
+            Date::parse(d).ok_or(String::from("invalid due date"))?,
+        )),
+    }
+}
+
+#[derive(Eq, Debug, Clone, Copy, Serialize)]
 pub struct TaskId(usize);
 
 impl TaskId {
+    /// Largest id given out, so the id after it can still be stored
+    pub const MAX: usize = usize::MAX - 1;
+
     pub fn new(id: usize) -> Self {
         Self(id)
     }
@@ -29,11 +394,21 @@ impl TaskId {
     }
 }
 
-#[derive(Debug)]
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
 pub struct Task {
     id: TaskId,
     status: TaskStatus,
     text: String,
+    due: Option<Date>,
+    priority: Priority,
+    tags: BTreeSet<String>,
+    parent: Option<TaskId>,
+    recurrence: Option<Recurrence>,
+    /// Tasks that must be done before this one can be
+    blockers: BTreeSet<TaskId>,
+    created: Option<Timestamp>,
+    completed: Option<Timestamp>,
+    modified: Option<Timestamp>,
 }
 
 impl Task {
@@ -41,26 +416,136 @@ impl Task {
         Self {
             id,
             status,
-            text: text.replace(';', ""),
+            text: String::from(text),
+            due: None,
+            priority: Priority::default(),
+            tags: BTreeSet::new(),
+            parent: None,
+            recurrence: None,
+            blockers: BTreeSet::new(),
+            created: None,
+            completed: None,
+            modified: None,
         }
     }
 
     pub fn new(id: TaskId, text: &str) -> Self {
-        Self {
-            id,
-            status: TaskStatus::Pending,
-            text: text.replace(';', ""),
+        Self::from_parts(id, TaskStatus::Pending, text)
+    }
+
+    /// Sets every attribute present in the given ones, adding their tags to
+    /// the existing ones, and removes the ones they remove
+    pub fn set_attributes(&mut self, attributes: &Attributes) {
+        for removal in &attributes.removed {
+            match removal {
+                Removal::Due => self.due = None,
+                Removal::Priority => self.priority = Priority::default(),
+                Removal::Tag(tag) => {
+                    self.tags.remove(tag);
+                },
+                Removal::Parent => self.parent = None,
+                Removal::Recurrence => self.recurrence = None,
+            }
+        }
+        if let Some(due) = attributes.due {
+            self.due = Some(due);
+        }
+        if let Some(priority) = attributes.priority {
+            self.priority = priority;
         }
+        self.tags.extend(attributes.tags.iter().cloned());
+        if let Some(parent) = attributes.parent {
+            self.parent = Some(parent);
+        }
+        if let Some(recurrence) = attributes.recurrence {
+            self.recurrence = Some(recurrence);
+        }
+    }
+
+    /// The same task under another id
+    pub fn with_id(self, id: TaskId) -> Self {
+        Self { id, ..self }
+    }
+
+    pub fn set_parent(&mut self, parent: Option<TaskId>) {
+        self.parent = parent;
+    }
+
+    pub fn set_blockers(&mut self, blockers: BTreeSet<TaskId>) {
+        self.blockers = blockers;
+    }
+
+    /// Switches a task that is not done between pending and blocked
+    pub fn set_blocked(&mut self, blocked: bool) {
+        self.status = match (&self.status, blocked) {
+            (TaskStatus::Done, _) => TaskStatus::Done,
+            (_, true) => TaskStatus::Blocked,
+            (_, false) => TaskStatus::Pending,
+        };
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = String::from(text);
     }
 
     pub fn r#do(&mut self) {
         self.status = TaskStatus::Done;
     }
 
+    /// Marks a recurring task as done, returning its next occurrence under
+    /// the given id.
+    /// The recurrence moves to the new occurrence, so doing this one again
+    /// does not create another.
+    pub fn recur(&mut self, id: TaskId) -> Option<Self> {
+        let recurrence = self.recurrence.take()?;
+        self.r#do();
+
+        let due = self.due.map(|d| recurrence.next(d));
+        Some(Self {
+            id,
+            status: TaskStatus::Pending,
+            due,
+            recurrence: Some(recurrence),
+            created: None,
+            completed: None,
+            modified: None,
+            ..self.clone()
+        })
+    }
+
     pub fn undo(&mut self) {
         self.status = TaskStatus::Pending;
     }
 
+    /// Records the task as created at the given time
+    pub fn set_created(&mut self, at: Timestamp) {
+        self.created = Some(at);
+        self.modified = Some(at);
+    }
+
+    /// Restores the times the task was created, completed and last changed,
+    /// as recorded elsewhere
+    pub fn set_times(
+        &mut self,
+        created: Option<Timestamp>,
+        completed: Option<Timestamp>,
+        modified: Option<Timestamp>,
+    ) {
+        self.created = created;
+        self.completed = completed;
+        self.modified = modified;
+    }
+
+    /// Records the task as changed at the given time, keeping the completion
+    /// time in line with its status
+    pub fn touch(&mut self, at: Timestamp) {
+        self.modified = Some(at);
+        match self.status {
+            TaskStatus::Done => self.completed = self.completed.or(Some(at)),
+            TaskStatus::Pending | TaskStatus::Blocked => self.completed = None,
+        }
+    }
+
     pub fn id(&self) -> &TaskId {
         &self.id
     }
@@ -73,15 +558,120 @@ impl Task {
         &self.text
     }
 
+    pub fn due(&self) -> Option<&Date> {
+        self.due.as_ref()
+    }
+
+    pub fn priority(&self) -> &Priority {
+        &self.priority
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    /// Task this one is a subtask of
+    pub fn parent(&self) -> Option<&TaskId> {
+        self.parent.as_ref()
+    }
+
+    pub fn recurrence(&self) -> Option<&Recurrence> {
+        self.recurrence.as_ref()
+    }
+
+    pub fn blockers(&self) -> &BTreeSet<TaskId> {
+        &self.blockers
+    }
+
+    /// Timestamps are missing on tasks stored before they were recorded
+    pub fn created(&self) -> Option<&Timestamp> {
+        self.created.as_ref()
+    }
+
+    pub fn completed(&self) -> Option<&Timestamp> {
+        self.completed.as_ref()
+    }
+
+    pub fn modified(&self) -> Option<&Timestamp> {
+        self.modified.as_ref()
+    }
+
+    /// Attributes that are not the default ones, as shown when listing tasks
+    pub fn attributes(&self) -> Attributes {
+        Attributes {
+            due: self.due,
+            priority: Some(self.priority).filter(|p| *p != Priority::default()),
+            tags: self.tags.clone(),
+            parent: self.parent,
+            recurrence: self.recurrence,
+            removed: BTreeSet::new(),
+        }
+    }
+
+    /// Builds a task from the fields written by [Task::to_csv].
+    /// Tasks written before attributes existed only have the first three, the
+    /// ones written before subtasks existed the first six, the ones written
+    /// before recurring tasks existed the first seven, the ones written
+    /// before timestamps existed the first eight and the ones written before
+    /// blockers existed the first eleven.
+    /// The error describes why the fields are not a valid task.
+    pub fn from_csv(fields: &[String]) -> Result<Self, String> {
+        if ![3, 6, 7, 8, 11, 12].contains(&fields.len()) {
+            return Err(String::from("missmatched parts"));
+        }
+
+        let id: usize = match fields[0].parse() {
+            Ok(i) => i,
+            Err(_) => return Err(String::from("invalid id")),
+        };
+
+        let status = TaskStatus::parse(&fields[1]).ok_or(String::from("invalid status"))?;
+
+        let mut task = Self::from_parts(TaskId::new(id), status, &fields[2]);
+        if fields.len() >= 6 {
+            task.due = parse_due_field(&fields[3])?;
+            task.priority = Priority::parse(&fields[4]).ok_or(String::from("invalid priority"))?;
+            task.tags = tags_from_field(&fields[5]);
+        }
+        if fields.len() >= 7 {
+            task.parent = parse_parent_field(&fields[6])?;
+        }
+        if fields.len() >= 8 {
+            task.recurrence = parse_recurrence_field(&fields[7])?;
+        }
+        if fields.len() >= 11 {
+            task.created = parse_timestamp_field(&fields[8])?;
+            task.completed = parse_timestamp_field(&fields[9])?;
+            task.modified = parse_timestamp_field(&fields[10])?;
+        }
+        if fields.len() == 12 {
+            task.blockers = parse_ids_field(&fields[11])?;
+        }
+
+        Ok(task)
+    }
+
     pub fn to_csv(&self) -> String {
-        format!("{};{};{}", self.id.val(), self.status.val(), self.text)
+        csv::encode_record(&[
+            self.id.val().to_string().as_str(),
+            self.status.val().as_str(),
+            &self.text,
+            &self.due.map_or(String::new(), |d| d.to_string()),
+            &self.priority.val(),
+            &tags_to_field(&self.tags),
+            &self.parent.map_or(String::new(), |p| p.val().to_string()),
+            &self.recurrence.map_or(String::new(), |r| r.val()),
+            &timestamp_to_field(self.created),
+            &timestamp_to_field(self.completed),
+            &timestamp_to_field(self.modified),
+            &ids_to_field(&self.blockers),
+        ])
     }
 }
 
 impl Hash for TaskId {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         state.write_usize(self.0);
-        state.finish();
     }
 }
 
@@ -109,24 +699,18 @@ impl PartialOrd for TaskId {
     }
 
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        if self.gt(other) {
-            Some(std::cmp::Ordering::Greater)
-        } else if self.lt(other) {
-            Some(std::cmp::Ordering::Less)
-        } else {
-            Some(std::cmp::Ordering::Equal)
-        }
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for TaskId {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
+        self.0.cmp(&other.0)
     }
 }
 
 impl fmt::Display for TaskId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)       
+        write!(f, "{}", self.0)
     }
-}
\ No newline at end of file
+}
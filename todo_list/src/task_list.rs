@@ -1,204 +1,1858 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
 use std::fs;
-use std::fs::File;
 use std::io;
-use std::io::Write;
-use std::path;
 
+use crate::activity::Activity;
 use crate::command::Command;
+use crate::command::Selection;
+use crate::date::Date;
+use crate::date::Timestamp;
+use crate::exchange;
+use crate::exchange::Format;
+use crate::history::Change;
+use crate::history::History;
+use crate::history::Transaction;
+use crate::journal::Journal;
 use crate::printer::Printer;
+use crate::query::ListQuery;
+use crate::reminders::Reminders;
+use crate::search::Match;
+use crate::stats::Stats;
+use crate::store;
+use crate::store::CsvStore;
+use crate::store::TaskStore;
+use crate::task::Attributes;
 use crate::task::Task;
 use crate::task::TaskId;
 use crate::task::TaskStatus;
 
+/// Number of journaled commands after which a new snapshot is saved
+const SNAPSHOT_INTERVAL: usize = 50;
+
+/// Per task result of a command acting on several tasks at once
+#[derive(PartialEq, Eq, Debug, Default)]
+pub struct Report {
+    pub succeeded: Vec<TaskId>,
+    pub failed: Vec<(TaskId, TaskError)>,
+    /// Tasks created along the way, like the next occurrence of a recurring
+    /// task
+    pub created: Vec<TaskId>,
+}
+
+/// What an executed [Command] did
+#[derive(PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Listed(Vec<Task>),
+    Created(TaskId),
+    Updated(Report),
+    Deleted(Report),
+    /// Old and new id of every task whose id changed
+    Renumbered(Vec<(TaskId, TaskId)>),
+    /// Recorded changes of a task, oldest first
+    Changes(TaskId, Vec<Change>),
+    Stats(Stats),
+    /// Tasks matching a search, most relevant first
+    Found(Vec<Match>),
+    Reminders(Reminders),
+    /// Tasks of every list with any matching one, by list name
+    ListedAll(Vec<(String, Vec<Task>)>),
+    /// Name of the list now in use
+    Switched(String),
+    Lists(Vec<String>),
+    /// Old id of the task, the list it was moved to and its id there
+    Moved(TaskId, String, TaskId),
+    /// File the tasks were written to and how many
+    Exported(String, usize),
+    /// Ids of the imported tasks
+    Imported(Vec<TaskId>),
+    Reverted(Vec<TaskId>),
+    Redone(Vec<TaskId>),
+    /// The command does not act on the task list, like [Command::Exit]
+    Ignored,
+}
+
+/// Any possible error while executing a [Command]
+#[derive(Debug)]
+pub enum TaskError {
+    UnknownTask(TaskId),
+    /// The task can not be deleted without deleting its subtasks too
+    HasSubtasks(TaskId),
+    /// The task can not be nested under itself or any of its subtasks
+    InvalidParent(TaskId),
+    /// The task can not be done before the given pending tasks
+    Blocked(TaskId, Vec<TaskId>),
+    /// The first task can not be blocked on the second one, which already
+    /// waits on it
+    BlockCycle(TaskId, TaskId),
+    AlreadyInList(String),
+    NothingToRevert,
+    NothingToRedo,
+    /// The task was changed after the command being reverted or redone
+    Conflict(TaskId),
+    /// Every id up to [TaskId::MAX] was given out
+    NoIdsLeft,
+    Io(io::Error),
+    /// The file to import could not be read
+    Import(io::Error),
+    /// The file to export to could not be written
+    Export(io::Error),
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTask(id) => write!(f, "Unknown task with key {}", id),
+            Self::HasSubtasks(id) => write!(
+                f,
+                "Task with key {} has subtasks, delete them first or add 'cascade'",
+                id
+            ),
+            Self::InvalidParent(id) => {
+                write!(f, "Task with key {} can not be nested under itself", id)
+            },
+            Self::Blocked(id, blockers) => {
+                let blockers: Vec<_> = blockers.iter().map(|b| b.to_string()).collect();
+                write!(
+                    f,
+                    "Task with key {} is blocked on pending task(s) {}",
+                    id,
+                    blockers.join(", ")
+                )
+            },
+            Self::BlockCycle(id, other) => write!(
+                f,
+                "Task with key {} can not be blocked on {}, which waits on it",
+                id, other
+            ),
+            Self::AlreadyInList(name) => write!(f, "Task is already in list '{}'", name),
+            Self::NothingToRevert => write!(f, "Nothing to revert"),
+            Self::NothingToRedo => write!(f, "Nothing to redo"),
+            Self::Conflict(id) => write!(
+                f,
+                "Task with key {} was changed since, so it can not be reverted or redone",
+                id
+            ),
+            Self::NoIdsLeft => write!(f, "No task ids left, renumber the tasks first"),
+            Self::Io(e) => write!(f, "Error while syncing tasks '{}'", e),
+            Self::Import(e) => write!(f, "Error while importing tasks '{}'", e),
+            Self::Export(e) => write!(f, "Error while exporting tasks '{}'", e),
+        }
+    }
+}
+
+impl PartialEq for TaskError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::UnknownTask(a), Self::UnknownTask(b)) => a == b,
+            (Self::HasSubtasks(a), Self::HasSubtasks(b)) => a == b,
+            (Self::InvalidParent(a), Self::InvalidParent(b)) => a == b,
+            (Self::Blocked(a, x), Self::Blocked(b, y)) => a == b && x == y,
+            (Self::BlockCycle(a, x), Self::BlockCycle(b, y)) => a == b && x == y,
+            (Self::AlreadyInList(a), Self::AlreadyInList(b)) => a == b,
+            (Self::NothingToRevert, Self::NothingToRevert) => true,
+            (Self::NothingToRedo, Self::NothingToRedo) => true,
+            (Self::Conflict(a), Self::Conflict(b)) => a == b,
+            (Self::NoIdsLeft, Self::NoIdsLeft) => true,
+            (Self::Io(a), Self::Io(b)) => a.kind() == b.kind(),
+            (Self::Import(a), Self::Import(b)) => a.kind() == b.kind(),
+            (Self::Export(a), Self::Export(b)) => a.kind() == b.kind(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TaskError {}
+
+impl Error for TaskError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) | Self::Import(e) | Self::Export(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TaskError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
 #[derive(Debug)]
 pub struct TaskList {
-    file: String,
+    store: Box<dyn TaskStore>,
+    journal: Option<Journal>,
+    history: History,
+    activity: Activity,
     printer: Box<Printer>,
     tasks: HashMap<TaskId, Task>,
+    /// Lowest id never given to a task, so deleted ids are not reused
+    next_id: usize,
+    /// Whether the tasks differ from the stored ones other than by the
+    /// journaled commands, like after replaying them
+    unsaved: bool,
 }
 
 impl TaskList {
     pub fn new(printer: Box<Printer>, file: &str) -> Result<Self, io::Error> {
-        let file = String::from(file);
-        let mut tasks = HashMap::new();
+        Self::with_store(printer, Box::new(CsvStore::new(file)))
+    }
+
+    pub fn with_store(printer: Box<Printer>, store: Box<dyn TaskStore>) -> Result<Self, io::Error> {
+        let mut task_list = Self {
+            store,
+            journal: None,
+            history: History::new(),
+            activity: Activity::new(),
+            printer,
+            tasks: HashMap::new(),
+            next_id: 1,
+            unsaved: false,
+        };
+        task_list.reload()?;
+
+        Ok(task_list)
+    }
+
+    /// Records every change in the given [Journal] instead of rewriting the
+    /// store each time.
+    /// Entries left by a previous session are replayed and folded into a new
+    /// snapshot first.
+    pub fn with_journal(self, journal: Journal) -> Result<Self, io::Error> {
+        let mut task_list = self.with_journal_replayed(journal)?;
+        task_list.snapshot()?;
+
+        Ok(task_list)
+    }
+
+    /// Replays the entries left by a previous session in the given [Journal]
+    /// without saving anything, for lists that are only read
+    pub fn with_journal_replayed(mut self, journal: Journal) -> Result<Self, io::Error> {
+        self.journal = Some(journal);
+
+        self.store.lock()?;
+        let result = self.reload();
+        self.store.unlock()?;
+
+        result.map(|_| self)
+    }
+
+    /// Keeps the commands that can be reverted in the given [History] instead
+    /// of a session only one
+    pub fn with_history(mut self, history: History) -> Result<Self, io::Error> {
+        self.history = history;
+        self.sync_history()?;
+
+        Ok(self)
+    }
+
+    /// Keeps what happens to the tasks in the given [Activity] instead of a
+    /// session only one
+    pub fn with_activity(self, activity: Activity) -> Self {
+        Self { activity, ..self }
+    }
+
+    /// Executes the command, returning what it did.
+    /// Changes are persisted before returning, a change that could not be
+    /// persisted is rolled back.
+    pub fn execute(&mut self, command: Command) -> Result<Outcome, TaskError> {
+        self.store.lock()?;
+
+        let result = self
+            .sync_with_store()
+            .and_then(|_| self.sync_history())
+            .map_err(TaskError::from)
+            .and_then(|_| self.execute_locked(command));
+
+        self.store.unlock()?;
+
+        result
+    }
+
+    /// Executes changing commands as a single change, so either every one of
+    /// them is applied or none is.
+    /// Each command is built from the outcomes of the previous ones, until
+    /// there is none left.
+    /// They are persisted in a new snapshot and reverted together.
+    pub fn execute_all(
+        &mut self,
+        commands: impl FnMut(&[Outcome]) -> Option<Command>,
+    ) -> Result<Vec<Outcome>, TaskError> {
+        self.store.lock()?;
+
+        let result = self
+            .sync_with_store()
+            .and_then(|_| self.sync_history())
+            .map_err(TaskError::from)
+            .and_then(|_| self.execute_all_locked(commands));
+
+        self.store.unlock()?;
 
-        if !path::Path::new(&file).exists() {
-            return Ok(Self {
-                file,
-                printer,
-                tasks,
-            });
+        result
+    }
+
+    /// Tasks matching the given query, in its order
+    pub fn query(&self, query: &ListQuery) -> Vec<&Task> {
+        query.apply(self.tasks.values())
+    }
+
+    pub fn get(&self, id: TaskId) -> Option<&Task> {
+        self.tasks.get(&id)
+    }
+
+    /// Saves the current tasks and empties the journal, if any, unless the
+    /// store already has them
+    pub fn snapshot(&mut self) -> Result<(), io::Error> {
+        self.store.lock()?;
+        let result = self
+            .sync_with_store()
+            .and_then(|_| self.snapshot_if_unsaved());
+        self.store.unlock()?;
+
+        result
+    }
+
+    fn execute_locked(&mut self, command: Command) -> Result<Outcome, TaskError> {
+        match command {
+            Command::List(query) => {
+                let tasks = self.query(&query).into_iter().cloned().collect();
+                return Ok(Outcome::Listed(tasks));
+            },
+            Command::History(id) => {
+                let (changes, warnings) = self.activity.changes(id)?;
+                for warning in warnings {
+                    self.printer.warning(&warning);
+                }
+                if changes.is_empty() && !self.tasks.contains_key(&id) {
+                    return Err(TaskError::UnknownTask(id));
+                }
+                return Ok(Outcome::Changes(id, changes));
+            },
+            Command::Stats => return Ok(Outcome::Stats(Stats::compute(self.tasks.values()))),
+            Command::Find(search) => return Ok(Outcome::Found(search.apply(self.tasks.values()))),
+            Command::Remind(days) => {
+                let reminders = Reminders::compute(self.tasks.values(), Date::today(), days);
+                return Ok(Outcome::Reminders(reminders));
+            },
+            Command::Export(format, file) => return self.export(format, file),
+            Command::Revert | Command::Redo => return self.travel(command),
+            _ => (),
         }
 
-        let content = fs::read_to_string(&file)?;
-        let lines = content.lines();
+        let command = self.with_recurrence_due(command);
+        let (before, next_id) = (self.tasks.clone(), self.next_id);
+        let at = Timestamp::now();
+        let outcome = self.apply(&command)?;
+        self.stamp(&before, at);
 
-        for line in lines {
-            let pieces: Vec<_> = line.split(';').collect();
+        let command = match (command, &outcome) {
+            // Nothing changed, so there is nothing to persist either
+            (_, Outcome::Updated(r) | Outcome::Deleted(r)) if r.succeeded.is_empty() => {
+                return Ok(outcome);
+            },
+            (_, Outcome::Imported(ids)) if ids.is_empty() => return Ok(outcome),
+            (command, Outcome::Updated(r) | Outcome::Deleted(r)) => resolved(command, r),
+            (command, _) => command,
+        };
+        let created = match outcome {
+            Outcome::Created(id) => Some(id),
+            _ => None,
+        };
+        if let Err(e) = self.persist(&command, created, at) {
+            (self.tasks, self.next_id) = (before, next_id);
+            return Err(e.into());
+        }
+        self.record(&before, at, true);
 
-            if pieces.len() != 3 {
-                printer
-                    .warning(format!("Ignoring task '{}' due to missmatched parts", line).as_str());
-                continue;
-            }
+        Ok(outcome)
+    }
 
-            let id: usize = match pieces[0].parse() {
-                Ok(i) => i,
-                Err(_) => {
-                    printer.warning(format!("Ignoring task '{}' due to invalid id", line).as_str());
-                    continue;
+    fn execute_all_locked(
+        &mut self,
+        mut commands: impl FnMut(&[Outcome]) -> Option<Command>,
+    ) -> Result<Vec<Outcome>, TaskError> {
+        let (before, next_id) = (self.tasks.clone(), self.next_id);
+        let at = Timestamp::now();
+
+        let mut outcomes = vec![];
+        while let Some(command) = commands(&outcomes) {
+            let command = self.with_recurrence_due(command);
+            match self.apply(&command) {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(e) => {
+                    (self.tasks, self.next_id) = (before, next_id);
+                    return Err(e);
                 },
+            }
+        }
+        self.stamp(&before, at);
+
+        if self.tasks == before {
+            return Ok(outcomes);
+        }
+        if let Err(e) = self.save_snapshot() {
+            (self.tasks, self.next_id) = (before, next_id);
+            return Err(e.into());
+        }
+        self.record(&before, at, true);
+
+        Ok(outcomes)
+    }
+
+    /// Recurring tasks are due today unless given a due date, so the next
+    /// occurrences have one to be computed from
+    fn with_recurrence_due(&self, command: Command) -> Command {
+        let needs_due = |attributes: &Attributes, task: Option<&Task>| {
+            attributes.recurrence.is_some()
+                && attributes.due.is_none()
+                && task.is_none_or(|t| t.due().is_none())
+        };
+
+        match command {
+            Command::Add(text, mut attributes) if needs_due(&attributes, None) => {
+                attributes.due = Some(Date::today());
+                Command::Add(text, attributes)
+            },
+            Command::Edit(id, text, mut attributes)
+                if needs_due(&attributes, self.tasks.get(&id)) =>
+            {
+                attributes.due = Some(Date::today());
+                Command::Edit(id, text, attributes)
+            },
+            command => command,
+        }
+    }
+
+    /// Moves back or forward through the [History] of commands
+    fn travel(&mut self, command: Command) -> Result<Outcome, TaskError> {
+        let transaction = match command {
+            Command::Revert => self.history.undo()?.ok_or(TaskError::NothingToRevert)?,
+            _ => self.history.redo()?.ok_or(TaskError::NothingToRedo)?,
+        };
+
+        // Tasks changed since, by a command left out of the history or another
+        // session, would lose those changes
+        let conflict = transaction.iter().find(|change| {
+            let expected = match command {
+                Command::Revert => &change.after,
+                _ => &change.before,
             };
+            self.tasks.get(&change.id) != expected.as_ref()
+        });
+        if let Some(change) = conflict {
+            let id = change.id;
+            self.put_back(&command);
+            return Err(TaskError::Conflict(id));
+        }
 
-            let status = match pieces[1] {
-                "pending" => TaskStatus::Pending,
-                "done" => TaskStatus::Done,
-                _ => {
-                    printer.warning(
-                        format!("Ignoring task '{}' due to invalid status", line).as_str(),
-                    );
-                    continue;
-                },
+        let before = self.tasks.clone();
+        let ids: Vec<_> = transaction.iter().map(|c| c.id).collect();
+        for change in transaction {
+            let state = match command {
+                Command::Revert => change.before,
+                _ => change.after,
             };
 
-            let text = pieces[2];
-            let id = TaskId::new(id);
-            let task = Task::from_parts(id, status, text);
-            tasks.insert(id, task);
+            match state {
+                Some(task) => self.tasks.insert(change.id, task),
+                None => self.tasks.remove(&change.id),
+            };
         }
 
-        Ok(Self {
-            file,
-            printer,
-            tasks,
-        })
-    }
+        let at = Timestamp::now();
+        if let Err(e) = self.persist(&command, None, at) {
+            self.tasks = before;
+            self.put_back(&command);
+            return Err(e.into());
+        }
+        self.record(&before, at, false);
 
-    pub fn execute(&mut self, command: Command) {
         match command {
-            Command::Add(text) => self.add_task(&text),
-            Command::Delete(id) => self.delete_task(id),
-            Command::Do(id) => self.do_task(id),
-            Command::List => self.print_tasks(),
-            Command::UnDo(id) => self.undo_task(id),
-            _ => (),
+            Command::Revert => Ok(Outcome::Reverted(ids)),
+            _ => Ok(Outcome::Redone(ids)),
         }
     }
 
-    fn do_task(&mut self, id: TaskId) {
-        let task = self.tasks.get_mut(&id);
-        if task.is_none() {
-            let msg = format!("Unknown task with key {}", id);
-            self.printer.warning(&msg);
-            return;
+    /// Puts the transaction taken by [TaskList::travel] back where it was
+    fn put_back(&mut self, command: &Command) {
+        let _ = match command {
+            Command::Revert => self.history.redo(),
+            _ => self.history.undo(),
+        };
+    }
+
+    /// Records the changes made since the given state in the activity of the
+    /// tasks and, unless they revert or redo a command, in the history
+    fn record(&mut self, before: &HashMap<TaskId, Task>, at: Timestamp, undoable: bool) {
+        let transaction = self.diff(before, Some(at));
+        if let Err(e) = self.activity.record(&transaction) {
+            let msg = format!("Error while saving activity '{}'", e);
+            self.printer.error(&msg);
+        }
+
+        if undoable {
+            if let Err(e) = self.history.record(transaction) {
+                let msg = format!("Error while saving history '{}'", e);
+                self.printer.error(&msg);
+            }
         }
+    }
 
-        let task = task.unwrap();
-        task.r#do();
+    /// Reloads the history, which other sessions may have changed
+    fn sync_history(&mut self) -> Result<(), io::Error> {
+        for warning in self.history.load()? {
+            self.printer.warning(&warning);
+        }
 
-        self.sync_to_file();
+        Ok(())
     }
 
-    fn undo_task(&mut self, id: TaskId) {
-        let task = self.tasks.get_mut(&id);
-        if task.is_none() {
-            let msg = format!("Unknown task with key {}", id);
-            self.printer.warning(&msg);
-            return;
+    /// Records the time on every task created or changed since the given state
+    fn stamp(&mut self, before: &HashMap<TaskId, Task>, at: Timestamp) {
+        for (id, task) in self.tasks.iter_mut() {
+            match before.get(id) {
+                // Like imported tasks, which keep the times they came with
+                None if task.created().is_some() => (),
+                None => {
+                    task.set_created(at);
+                    task.touch(at);
+                },
+                Some(previous) if previous != task => task.touch(at),
+                _ => (),
+            }
         }
+    }
 
-        let task = task.unwrap();
-        task.undo();
+    /// Every task that differs between the given state and the current one
+    fn diff(&self, before: &HashMap<TaskId, Task>, at: Option<Timestamp>) -> Transaction {
+        let ids: BTreeSet<_> = before.keys().chain(self.tasks.keys()).collect();
 
-        self.sync_to_file();
+        ids.into_iter()
+            .filter(|id| before.get(id) != self.tasks.get(id))
+            .map(|id| Change {
+                id: *id,
+                before: before.get(id).cloned(),
+                after: self.tasks.get(id).cloned(),
+                at,
+            })
+            .collect()
     }
 
-    fn print_tasks(&self) {
-        let mut ids: Vec<_> = self.tasks.keys().collect();
-        ids.sort();
+    /// Reloads the tasks if another process changed them since they were last
+    /// read or written, so the next change is applied on top of theirs
+    fn sync_with_store(&mut self) -> Result<(), io::Error> {
+        let journal_modified = match &self.journal {
+            Some(journal) => journal.is_modified()?,
+            None => false,
+        };
 
-        for id in ids {
-            let task = self.tasks.get(id).unwrap();
-            let str = format!("{}\t{}\t\t{}", task.id(), task.status().val(), task.text());
-            self.printer.notice(&str);
+        if !journal_modified && !self.store.is_modified()? {
+            return Ok(());
         }
+
+        self.printer
+            .warning("Tasks were modified by another session, reloading them");
+        self.reload()
     }
 
-    fn add_task(&mut self, text: &str) {
-        let id = self.get_next_task_id();
-        let task = Task::new(id, text);
-        self.tasks.insert(id, task);
+    /// Replaces the in memory tasks with the stored ones, replaying the journal
+    /// on top if there is one
+    fn reload(&mut self) -> Result<(), io::Error> {
+        let snapshot = self.store.load()?;
 
-        let msg = format!("Task successfully created with id {}", id);
-        self.printer.notice(&msg);
+        for warning in snapshot.warnings {
+            self.printer.warning(&warning);
+        }
 
-        self.sync_to_file();
-    }
+        self.tasks = snapshot
+            .tasks
+            .into_iter()
+            .map(|task| (*task.id(), task))
+            .collect();
+        self.next_id = snapshot.next_id.map_or(1, |id| id.val());
+        self.unsaved = false;
 
-    fn get_next_task_id(&mut self) -> TaskId {
-        let mut ids: Vec<_> = self.tasks.keys().collect();
-        if ids.is_empty() {
-            return TaskId::new(1);
+        let replay = match self.journal.as_mut() {
+            Some(journal) => journal.load()?,
+            None => return Ok(()),
+        };
+        let loaded = self.tasks.clone();
+
+        for warning in replay.warnings {
+            self.printer.warning(&warning);
         }
 
-        ids.sort();
-        for index in 0..ids[ids.len() - 1].val() {
-            let key = ids[index].val();
-            let next_key = match ids.get(index + 1) {
-                Some(k) => k.val(),
-                None => break,
-            };
+        for entry in replay.entries {
+            let before = self.tasks.clone();
+            let at = entry.at.unwrap_or_else(Timestamp::now);
 
-            if key != next_key - 1 {
-                return TaskId::new(key + 1);
+            if let (Command::Add(text, attributes), Some(id)) = (&entry.command, entry.created) {
+                self.tasks.entry(id).or_insert_with(|| {
+                    let mut task = Task::new(id, text);
+                    task.set_attributes(attributes);
+                    task
+                });
+                self.next_id = self.next_id.max(id.val().saturating_add(1));
+            } else if let Err(e) = self.apply(&entry.command) {
+                let msg = format!("Ignoring journal entry due to '{}'", e);
+                self.printer.warning(&msg);
             }
+
+            self.stamp(&before, at);
         }
+        self.unsaved = self.tasks != loaded;
 
-        TaskId::new(ids.last().unwrap().val() + 1)
+        Ok(())
     }
 
-    fn delete_task(&mut self, id: TaskId) {
-        match self.tasks.remove(&id) {
-            Some(_) => {
-                let msg = format!("Task with key {} successfully deleted", id);
-                self.printer.notice(&msg);
+    /// Applies a changing command to the in memory tasks only
+    fn apply(&mut self, command: &Command) -> Result<Outcome, TaskError> {
+        let outcome = match command {
+            Command::Add(text, attributes) => self.add_task(text, attributes).map(Outcome::Created),
+            Command::Delete(selection, cascade) => {
+                // Subtasks go first, so selecting a whole tree does not need a cascade
+                let mut ids = self.selected_ids(selection);
+                ids.sort_by_key(|id| Reverse(self.depth(*id)));
+
+                self.for_each_task(ids, |task_list, id| task_list.delete_task(id, *cascade))
+                    .map(Outcome::Deleted)
             },
-            None => {
-                let msg = format!("Unknown task with key {}", id);
-                self.printer.warning(&msg);
+            Command::Do(selection) => {
+                let existing: HashSet<_> = self.tasks.keys().copied().collect();
+                let mut report = self.for_each_task(self.selected_ids(selection), Self::do_task)?;
+
+                report.created = self
+                    .tasks
+                    .keys()
+                    .filter(|id| !existing.contains(id))
+                    .copied()
+                    .collect();
+                report.created.sort();
+
+                Ok(Outcome::Updated(report))
+            },
+            Command::UnDo(selection) => self
+                .for_each_task(self.selected_ids(selection), Self::undo_task)
+                .map(Outcome::Updated),
+            Command::Edit(id, text, attributes) => {
+                self.edit_task(*id, text.as_deref(), attributes)?;
+                Ok(Outcome::Updated(Report {
+                    succeeded: vec![*id],
+                    ..Report::default()
+                }))
+            },
+            Command::Block(id, other) => {
+                self.block_task(*id, *other)?;
+                Ok(Outcome::Updated(Report {
+                    succeeded: vec![*id],
+                    ..Report::default()
+                }))
             },
+            Command::Unblock(id, other) => {
+                self.unblock_task(*id, *other)?;
+                Ok(Outcome::Updated(Report {
+                    succeeded: vec![*id],
+                    ..Report::default()
+                }))
+            },
+            Command::Renumber => Ok(Outcome::Renumbered(self.renumber())),
+            Command::Import(file) => self.import(file).map(Outcome::Imported),
+            _ => Ok(Outcome::Ignored),
+        };
+        self.update_blocked();
+
+        outcome
+    }
+
+    fn selected_ids(&self, selection: &Selection) -> Vec<TaskId> {
+        match selection {
+            Selection::Ids(ids) => ids.clone(),
+            Selection::Done => {
+                let mut ids: Vec<_> = self
+                    .tasks
+                    .values()
+                    .filter(|t| t.status() == &TaskStatus::Done)
+                    .map(|t| *t.id())
+                    .collect();
+                ids.sort();
+                ids
+            },
+        }
+    }
+
+    /// Applies the action to every given task, failing only when it failed for
+    /// all of them
+    fn for_each_task(
+        &mut self,
+        ids: Vec<TaskId>,
+        mut action: impl FnMut(&mut Self, TaskId) -> Result<(), TaskError>,
+    ) -> Result<Report, TaskError> {
+        let mut report = Report::default();
+        for id in ids {
+            match action(self, id) {
+                Ok(_) => report.succeeded.push(id),
+                Err(e) => report.failed.push((id, e)),
+            }
+        }
+
+        if report.succeeded.is_empty() && !report.failed.is_empty() {
+            return Err(report.failed.remove(0).1);
         }
 
-        self.sync_to_file();
+        Ok(report)
+    }
+
+    fn subtasks(&self, id: TaskId) -> Vec<TaskId> {
+        self.tasks
+            .values()
+            .filter(|t| t.parent() == Some(&id))
+            .map(|t| *t.id())
+            .collect()
+    }
+
+    /// Ids of the tasks above the given one, closest first
+    fn ancestors(&self, id: TaskId) -> Vec<TaskId> {
+        let mut ancestors = vec![];
+        let mut current = self.tasks.get(&id).and_then(|t| t.parent());
+
+        // Bounded by the number of tasks in case the stored parents loop
+        while let Some(parent) = current {
+            if ancestors.len() > self.tasks.len() || !self.tasks.contains_key(parent) {
+                break;
+            }
+            ancestors.push(*parent);
+            current = self.tasks.get(parent).and_then(|t| t.parent());
+        }
+
+        ancestors
+    }
+
+    fn depth(&self, id: TaskId) -> usize {
+        self.ancestors(id).len()
     }
 
-    fn sync_to_file(&self) {
-        let mut file = match File::create(&self.file) {
-            Ok(f) => f,
-            Err(e) => {
-                let msg = format!("Error while opening file to sync '{}'", e);
+    fn persist(
+        &mut self,
+        command: &Command,
+        created: Option<TaskId>,
+        at: Timestamp,
+    ) -> Result<(), io::Error> {
+        let result = match (&mut self.journal, command) {
+            (_, Command::Revert | Command::Redo | Command::Renumber | Command::Import(_)) => {
+                self.save_snapshot()
+            },
+            (Some(journal), _) => journal.record(command, created, at),
+            (None, Command::Add(..)) => {
+                let task = self.tasks.get(&created.unwrap()).unwrap();
+                self.store.append(task)
+            },
+            (None, _) => self.save(),
+        };
+        result?;
+
+        let journal_len = self.journal.as_ref().map_or(0, |j| j.len());
+        if journal_len >= SNAPSHOT_INTERVAL {
+            if let Err(e) = self.save_snapshot() {
+                let msg = format!("Error while saving snapshot '{}'", e);
                 self.printer.error(&msg);
-                return;
             }
+        }
+
+        Ok(())
+    }
+
+    /// Parents are completed as well once all their subtasks are
+    fn do_task(&mut self, id: TaskId) -> Result<(), TaskError> {
+        if !self.tasks.contains_key(&id) {
+            return Err(TaskError::UnknownTask(id));
+        }
+        let blockers = self.pending_blockers(id);
+        if !blockers.is_empty() {
+            return Err(TaskError::Blocked(id, blockers));
+        }
+        self.complete_task(id)?;
+
+        for parent in self.ancestors(id) {
+            let all_done = self
+                .subtasks(parent)
+                .iter()
+                .all(|s| self.tasks[s].status() == &TaskStatus::Done);
+            if !all_done || !self.pending_blockers(parent).is_empty() {
+                break;
+            }
+            self.complete_task(parent)?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks the task as done, creating its next occurrence if it recurs
+    fn complete_task(&mut self, id: TaskId) -> Result<(), TaskError> {
+        if self.tasks[&id].recurrence().is_none() {
+            self.tasks.get_mut(&id).unwrap().r#do();
+            return Ok(());
+        }
+
+        let next_id = self.new_task_id()?;
+        let task = self.tasks.get_mut(&id).unwrap();
+        if let Some(next) = task.recur(next_id) {
+            self.tasks.insert(next_id, next);
+            self.next_id = next_id.val() + 1;
+        }
+
+        Ok(())
+    }
+
+    fn undo_task(&mut self, id: TaskId) -> Result<(), TaskError> {
+        match self.tasks.get_mut(&id) {
+            Some(task) => task.undo(),
+            None => return Err(TaskError::UnknownTask(id)),
+        }
+
+        Ok(())
+    }
+
+    fn edit_task(
+        &mut self,
+        id: TaskId,
+        text: Option<&str>,
+        attributes: &Attributes,
+    ) -> Result<(), TaskError> {
+        if !self.tasks.contains_key(&id) {
+            return Err(TaskError::UnknownTask(id));
+        }
+        if let Some(parent) = attributes.parent {
+            self.check_parent(parent)?;
+            if parent == id || self.ancestors(parent).contains(&id) {
+                return Err(TaskError::InvalidParent(id));
+            }
+        }
+
+        let task = self.tasks.get_mut(&id).unwrap();
+        if let Some(text) = text {
+            task.set_text(text);
+        }
+        task.set_attributes(attributes);
+
+        Ok(())
+    }
+
+    fn block_task(&mut self, id: TaskId, other: TaskId) -> Result<(), TaskError> {
+        for id in [id, other] {
+            if !self.tasks.contains_key(&id) {
+                return Err(TaskError::UnknownTask(id));
+            }
+        }
+        if id == other || self.waits_on(other, id) {
+            return Err(TaskError::BlockCycle(id, other));
+        }
+
+        let task = self.tasks.get_mut(&id).unwrap();
+        let mut blockers = task.blockers().clone();
+        blockers.insert(other);
+        task.set_blockers(blockers);
+
+        Ok(())
+    }
+
+    fn unblock_task(&mut self, id: TaskId, other: Option<TaskId>) -> Result<(), TaskError> {
+        let task = self.tasks.get_mut(&id).ok_or(TaskError::UnknownTask(id))?;
+        let blockers = match other {
+            Some(other) => task
+                .blockers()
+                .iter()
+                .filter(|b| **b != other)
+                .copied()
+                .collect(),
+            None => BTreeSet::new(),
         };
+        task.set_blockers(blockers);
 
-        let mut ids: Vec<_> = self.tasks.keys().collect();
-        ids.sort();
+        Ok(())
+    }
+
+    /// Whether the task waits on the other one, directly or through the tasks
+    /// it is blocked on
+    fn waits_on(&self, id: TaskId, other: TaskId) -> bool {
+        let mut visited = HashSet::new();
+        let mut pending = vec![id];
+
+        while let Some(current) = pending.pop() {
+            if !visited.insert(current) {
+                continue;
+            }
+
+            match self.tasks.get(&current) {
+                Some(task) if task.blockers().contains(&other) => return true,
+                Some(task) => pending.extend(task.blockers().iter().copied()),
+                None => (),
+            }
+        }
+
+        false
+    }
+
+    /// Tasks the given one is blocked on that are not done yet
+    fn pending_blockers(&self, id: TaskId) -> Vec<TaskId> {
+        self.tasks[&id]
+            .blockers()
+            .iter()
+            .filter(|b| {
+                self.tasks
+                    .get(b)
+                    .is_some_and(|t| t.status() != &TaskStatus::Done)
+            })
+            .copied()
+            .collect()
+    }
 
+    /// Blocks every task waiting on a pending one and unblocks the rest
+    fn update_blocked(&mut self) {
+        let ids: Vec<_> = self.tasks.keys().copied().collect();
         for id in ids {
-            let task = self.tasks.get(id).unwrap();
-            let str = format!("{}\n", task.to_csv());
-            if let Err(e) = file.write(str.as_bytes()) {
-                let msg = format!("Error while printing task '{}' to file '{}'", task.text(), e);
-                self.printer.error(&msg);
-                return;
+            let blocked = !self.pending_blockers(id).is_empty();
+            self.tasks.get_mut(&id).unwrap().set_blocked(blocked);
+        }
+    }
+
+    fn check_parent(&self, parent: TaskId) -> Result<(), TaskError> {
+        match self.tasks.contains_key(&parent) {
+            true => Ok(()),
+            false => Err(TaskError::UnknownTask(parent)),
+        }
+    }
+
+    fn add_task(&mut self, text: &str, attributes: &Attributes) -> Result<TaskId, TaskError> {
+        if let Some(parent) = attributes.parent {
+            self.check_parent(parent)?;
+        }
+
+        let id = self.new_task_id()?;
+        let mut task = Task::new(id, text);
+        task.set_attributes(attributes);
+        self.tasks.insert(id, task);
+        self.next_id = id.val() + 1;
+
+        Ok(id)
+    }
+
+    fn get_next_task_id(&self) -> TaskId {
+        let after_last = self
+            .tasks
+            .keys()
+            .max()
+            .map_or(1, |id| id.val().saturating_add(1));
+        TaskId::new(self.next_id.max(after_last))
+    }
+
+    /// The id for a new task, as long as there is one left
+    fn new_task_id(&self) -> Result<TaskId, TaskError> {
+        let id = self.get_next_task_id();
+        match id.val() <= TaskId::MAX {
+            true => Ok(id),
+            false => Err(TaskError::NoIdsLeft),
+        }
+    }
+
+    fn renumber(&mut self) -> Vec<(TaskId, TaskId)> {
+        let mut ids: Vec<_> = self.tasks.keys().copied().collect();
+        ids.sort();
+
+        let mut renumbered = vec![];
+        let mut tasks = HashMap::new();
+        for (index, id) in ids.into_iter().enumerate() {
+            let new_id = TaskId::new(index + 1);
+            let task = self.tasks.remove(&id).unwrap();
+
+            if new_id != id {
+                renumbered.push((id, new_id));
             }
+            tasks.insert(new_id, task.with_id(new_id));
         }
 
-        if let Err(e) = file.flush() {
-            let msg = format!("Error while flushing buffer '{}'", e);
-            self.printer.error(&msg);
+        let new_ids: HashMap<_, _> = renumbered.iter().copied().collect();
+        let new_id = |id: &TaskId| *new_ids.get(id).unwrap_or(id);
+        for task in tasks.values_mut() {
+            task.set_parent(task.parent().map(new_id));
+            task.set_blockers(task.blockers().iter().map(new_id).collect());
+        }
+
+        self.next_id = tasks.len() + 1;
+        self.tasks = tasks;
+
+        renumbered
+    }
+
+    fn delete_task(&mut self, id: TaskId, cascade: bool) -> Result<(), TaskError> {
+        if !self.tasks.contains_key(&id) {
+            return Err(TaskError::UnknownTask(id));
+        }
+
+        let subtasks = self.subtasks(id);
+        if !subtasks.is_empty() && !cascade {
+            return Err(TaskError::HasSubtasks(id));
+        }
+
+        self.tasks.remove(&id);
+        for task in self.tasks.values_mut() {
+            if task.blockers().contains(&id) {
+                let blockers = task
+                    .blockers()
+                    .iter()
+                    .filter(|b| **b != id)
+                    .copied()
+                    .collect();
+                task.set_blockers(blockers);
+            }
+        }
+        for subtask in subtasks {
+            self.delete_task(subtask, true)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes every task to the given file, subtasks after their parent
+    fn export(&self, format: Format, file: String) -> Result<Outcome, TaskError> {
+        let tasks = self.query(&ListQuery::default());
+        let content = exchange::export(format, &tasks);
+        store::write_atomically(&file, content.as_bytes()).map_err(TaskError::Export)?;
+
+        Ok(Outcome::Exported(file, tasks.len()))
+    }
+
+    /// Adds the tasks in the given file, warning about the ones that could not
+    /// be read.
+    /// Imported tasks keep their id unless this list already gave it out.
+    fn import(&mut self, file: &str) -> Result<Vec<TaskId>, TaskError> {
+        let content = fs::read_to_string(file).map_err(TaskError::Import)?;
+        let snapshot = exchange::import(Format::from_file(file), &content);
+
+        for warning in snapshot.warnings {
+            self.printer.warning(&warning);
+        }
+
+        let mut imported = vec![];
+        let mut ids = HashMap::new();
+        for task in snapshot.tasks {
+            let id = *task.id();
+            let new_id = match self.tasks.contains_key(&id) || id.val() < self.next_id {
+                true => self.new_task_id(),
+                false if id.val() <= TaskId::MAX => Ok(id),
+                false => Err(TaskError::NoIdsLeft),
+            };
+            let new_id = match new_id {
+                Ok(new_id) => new_id,
+                Err(e) => {
+                    let msg = format!("Ignoring task with id {} due to '{}'", id, e);
+                    self.printer.warning(&msg);
+                    continue;
+                },
+            };
+            ids.entry(id).or_insert(new_id);
+            imported.push(new_id);
+            self.tasks.insert(new_id, task.with_id(new_id));
+            self.next_id = self.next_id.max(new_id.val() + 1);
+        }
+
+        // Parents and blockers outside of the imported tasks are dropped
+        for new_id in &imported {
+            let task = self.tasks.get_mut(new_id).unwrap();
+            let parent = task.parent().and_then(|p| ids.get(p)).copied();
+            task.set_parent(parent);
+            let blockers = task.blockers().iter().filter_map(|b| ids.get(b)).copied();
+            task.set_blockers(blockers.collect());
+        }
+
+        Ok(imported)
+    }
+
+    fn save(&mut self) -> Result<(), io::Error> {
+        let mut ids: Vec<_> = self.tasks.keys().collect();
+        ids.sort();
+
+        let tasks: Vec<_> = ids.iter().map(|id| self.tasks.get(id).unwrap()).collect();
+        self.store.save(&tasks, self.get_next_task_id())?;
+        self.unsaved = false;
+
+        Ok(())
+    }
+
+    fn save_snapshot(&mut self) -> Result<(), io::Error> {
+        self.save()?;
+
+        match self.journal.as_mut() {
+            Some(journal) => journal.clear(),
+            None => Ok(()),
+        }
+    }
+
+    fn snapshot_if_unsaved(&mut self) -> Result<(), io::Error> {
+        match &self.journal {
+            Some(journal) if !journal.is_empty() => self.save_snapshot(),
+            _ if self.unsaved => self.save_snapshot(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The command acting on the tasks it actually changed, so replaying it does
+/// not depend on the state it was first applied to
+fn resolved(command: Command, report: &Report) -> Command {
+    let selection = Selection::Ids(report.succeeded.clone());
+
+    match command {
+        Command::Do(_) => Command::Do(selection),
+        Command::UnDo(_) => Command::UnDo(selection),
+        Command::Delete(_, cascade) => Command::Delete(selection, cascade),
+        command => command,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::store::MemoryStore;
+    use crate::store::Snapshot;
+
+    fn task_list() -> TaskList {
+        TaskList::with_store(Box::default(), Box::new(MemoryStore::new())).unwrap()
+    }
+
+    fn add(text: &str) -> Command {
+        Command::Add(String::from(text), Attributes::default())
+    }
+
+    fn list(task_list: &mut TaskList) -> Vec<Task> {
+        match task_list.execute(Command::List(ListQuery::default())) {
+            Ok(Outcome::Listed(tasks)) => tasks,
+            other => panic!("Unexpected list result {:?}", other),
+        }
+    }
+
+    fn succeeded(ids: &[usize]) -> Report {
+        Report {
+            succeeded: ids.iter().map(|i| TaskId::new(*i)).collect(),
+            ..Report::default()
+        }
+    }
+
+    #[test]
+    fn it_returns_the_outcome_of_each_command() {
+        let mut task_list = task_list();
+        let id = TaskId::new(1);
+
+        assert_eq!(
+            task_list.execute(add("First")).unwrap(),
+            Outcome::Created(id)
+        );
+        assert_eq!(
+            task_list.execute(Command::Do(id.into())).unwrap(),
+            Outcome::Updated(succeeded(&[1]))
+        );
+        assert_eq!(list(&mut task_list)[0].status(), &TaskStatus::Done);
+        assert_eq!(
+            task_list
+                .execute(Command::Delete(id.into(), false))
+                .unwrap(),
+            Outcome::Deleted(succeeded(&[1]))
+        );
+        assert!(list(&mut task_list).is_empty());
+        assert_eq!(task_list.execute(Command::Exit).unwrap(), Outcome::Ignored);
+    }
+
+    #[test]
+    fn it_fails_on_unknown_tasks() {
+        let mut task_list = task_list();
+        let id = TaskId::new(7);
+
+        for command in [
+            Command::Do(id.into()),
+            Command::UnDo(id.into()),
+            Command::Delete(id.into(), false),
+        ] {
+            let result = task_list.execute(command);
+            assert!(matches!(result, Err(TaskError::UnknownTask(i)) if i == id));
+        }
+    }
+
+    #[test]
+    fn it_never_reuses_task_ids() {
+        let mut task_list = task_list();
+        for text in ["First", "Second", "Third"] {
+            task_list.execute(add(text)).unwrap();
+        }
+        task_list
+            .execute(Command::Delete(TaskId::new(2).into(), false))
+            .unwrap();
+        task_list
+            .execute(Command::Delete(TaskId::new(3).into(), false))
+            .unwrap();
+
+        assert_eq!(
+            task_list.execute(add("Fourth")).unwrap(),
+            Outcome::Created(TaskId::new(4))
+        );
+
+        // The counter survives reloading the tasks
+        let mut task_list = TaskList::with_store(
+            Box::default(),
+            Box::new(MemoryStore::with_tasks(vec![Task::new(
+                TaskId::new(1),
+                "First",
+            )])),
+        )
+        .unwrap();
+        task_list.execute(add("Second")).unwrap();
+        task_list
+            .execute(Command::Delete(TaskId::new(2).into(), false))
+            .unwrap();
+        task_list.reload().unwrap();
+        assert_eq!(
+            task_list.execute(add("Third")).unwrap(),
+            Outcome::Created(TaskId::new(3))
+        );
+    }
+
+    #[test]
+    fn it_blocks_tasks_until_their_blockers_are_done() {
+        let mut task_list = task_list();
+        for text in ["Order parts", "Assemble", "Ship"] {
+            task_list.execute(add(text)).unwrap();
+        }
+        let id = TaskId::new;
+        let statuses = |task_list: &mut TaskList| -> Vec<TaskStatus> {
+            list(task_list).iter().map(|t| t.status().clone()).collect()
+        };
+
+        task_list.execute(Command::Block(id(2), id(1))).unwrap();
+        task_list.execute(Command::Block(id(3), id(2))).unwrap();
+        assert_eq!(
+            statuses(&mut task_list),
+            vec![
+                TaskStatus::Pending,
+                TaskStatus::Blocked,
+                TaskStatus::Blocked
+            ]
+        );
+        assert_eq!(
+            task_list.execute(Command::Do(id(2).into())),
+            Err(TaskError::Blocked(id(2), vec![id(1)]))
+        );
+        assert_eq!(
+            task_list.execute(Command::Block(id(1), id(3))),
+            Err(TaskError::BlockCycle(id(1), id(3)))
+        );
+        assert_eq!(
+            task_list.execute(Command::Block(id(1), id(1))),
+            Err(TaskError::BlockCycle(id(1), id(1)))
+        );
+
+        task_list.execute(Command::Do(id(1).into())).unwrap();
+        assert_eq!(
+            statuses(&mut task_list),
+            vec![TaskStatus::Done, TaskStatus::Pending, TaskStatus::Blocked]
+        );
+        task_list.execute(Command::UnDo(id(1).into())).unwrap();
+        assert_eq!(list(&mut task_list)[1].status(), &TaskStatus::Blocked);
+
+        task_list
+            .execute(Command::Delete(id(1).into(), false))
+            .unwrap();
+        task_list.execute(Command::Unblock(id(3), None)).unwrap();
+        assert_eq!(
+            statuses(&mut task_list),
+            vec![TaskStatus::Pending, TaskStatus::Pending]
+        );
+        assert!(list(&mut task_list)[0].blockers().is_empty());
+    }
+
+    #[test]
+    fn it_renumbers_tasks() {
+        let mut task_list = task_list();
+        for text in ["First", "Second", "Third"] {
+            task_list.execute(add(text)).unwrap();
         }
+        task_list.execute(add_subtask("Fourth", 3)).unwrap();
+        task_list
+            .execute(Command::Delete(TaskId::new(2).into(), false))
+            .unwrap();
+
+        assert_eq!(
+            task_list.execute(Command::Renumber).unwrap(),
+            Outcome::Renumbered(vec![
+                (TaskId::new(3), TaskId::new(2)),
+                (TaskId::new(4), TaskId::new(3)),
+            ])
+        );
+        let texts: Vec<_> = list(&mut task_list)
+            .iter()
+            .map(|t| (t.id().val(), String::from(t.text())))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                (1, String::from("First")),
+                (2, String::from("Third")),
+                (3, String::from("Fourth")),
+            ]
+        );
+        assert_eq!(list(&mut task_list)[2].parent(), Some(&TaskId::new(2)));
+        assert_eq!(
+            task_list.execute(add("Fifth")).unwrap(),
+            Outcome::Created(TaskId::new(4))
+        );
+
+        task_list.execute(Command::Revert).unwrap();
+        task_list.execute(Command::Revert).unwrap();
+        assert_eq!(list(&mut task_list)[1].text(), "Third");
+        assert_eq!(list(&mut task_list)[1].id(), &TaskId::new(3));
+    }
+
+    fn add_subtask(text: &str, parent: usize) -> Command {
+        let mut attributes = Attributes::default();
+        attributes.parse_arg(&format!("parent:{}", parent)).unwrap();
+        Command::Add(String::from(text), attributes)
+    }
+
+    #[test]
+    fn it_nests_subtasks_under_existing_tasks() {
+        let mut task_list = task_list();
+        task_list.execute(add("Release")).unwrap();
+        task_list.execute(add_subtask("Write tests", 1)).unwrap();
+        task_list.execute(add_subtask("Run tests", 2)).unwrap();
+
+        assert_eq!(
+            task_list.execute(add_subtask("Orphan", 9)),
+            Err(TaskError::UnknownTask(TaskId::new(9)))
+        );
+
+        let mut attributes = Attributes::default();
+        attributes.parse_arg("parent:3").unwrap();
+        let edit = Command::Edit(TaskId::new(1), None, attributes);
+        assert_eq!(
+            task_list.execute(edit),
+            Err(TaskError::InvalidParent(TaskId::new(1)))
+        );
+    }
+
+    #[test]
+    fn it_completes_parents_with_every_subtask_done() {
+        let mut task_list = task_list();
+        task_list.execute(add("Release")).unwrap();
+        task_list.execute(add_subtask("Write tests", 1)).unwrap();
+        task_list.execute(add_subtask("Write docs", 1)).unwrap();
+        task_list.execute(add_subtask("Proofread docs", 3)).unwrap();
+
+        task_list
+            .execute(Command::Do(TaskId::new(2).into()))
+            .unwrap();
+        assert_eq!(list(&mut task_list)[0].status(), &TaskStatus::Pending);
+
+        task_list
+            .execute(Command::Do(TaskId::new(4).into()))
+            .unwrap();
+        let statuses: Vec<_> = list(&mut task_list)
+            .iter()
+            .map(|t| t.status().clone())
+            .collect();
+        assert_eq!(statuses, vec![TaskStatus::Done; 4]);
+    }
+
+    #[test]
+    fn it_deletes_subtasks_only_on_cascade() {
+        let mut task_list = task_list();
+        task_list.execute(add("Release")).unwrap();
+        task_list.execute(add_subtask("Write tests", 1)).unwrap();
+        task_list.execute(add_subtask("Run tests", 2)).unwrap();
+        task_list.execute(add("Unrelated")).unwrap();
+
+        assert_eq!(
+            task_list.execute(Command::Delete(TaskId::new(1).into(), false)),
+            Err(TaskError::HasSubtasks(TaskId::new(1)))
+        );
+
+        let whole_tree = Selection::Ids(vec![TaskId::new(1), TaskId::new(3), TaskId::new(2)]);
+        assert_eq!(
+            task_list
+                .execute(Command::Delete(whole_tree, false))
+                .unwrap(),
+            Outcome::Deleted(succeeded(&[3, 2, 1]))
+        );
+        task_list.execute(Command::Revert).unwrap();
+
+        task_list
+            .execute(Command::Delete(TaskId::new(1).into(), true))
+            .unwrap();
+        let ids: Vec<_> = list(&mut task_list).iter().map(|t| t.id().val()).collect();
+        assert_eq!(ids, vec![4]);
+    }
+
+    #[test]
+    fn it_creates_the_next_occurrence_of_recurring_tasks() {
+        let mut task_list = task_list();
+        let mut attributes = Attributes::default();
+        for arg in ["due:2026-10-16", "every:weekday", "#standup", "parent:1"] {
+            attributes.parse_arg(arg).unwrap();
+        }
+        task_list.execute(add("Meetings")).unwrap();
+        task_list
+            .execute(Command::Add(String::from("Standup notes"), attributes))
+            .unwrap();
+
+        let mut expected = succeeded(&[2]);
+        expected.created.push(TaskId::new(3));
+        assert_eq!(
+            task_list
+                .execute(Command::Do(TaskId::new(2).into()))
+                .unwrap(),
+            Outcome::Updated(expected)
+        );
+
+        let tasks = list(&mut task_list);
+        assert_eq!(tasks[0].status(), &TaskStatus::Pending);
+        assert_eq!(tasks[1].status(), &TaskStatus::Done);
+        assert_eq!(tasks[1].recurrence(), None);
+        assert_eq!(tasks[2].text(), "Standup notes");
+        assert_eq!(tasks[2].status(), &TaskStatus::Pending);
+        assert_eq!(tasks[2].due(), Date::new(2026, 10, 19).as_ref());
+        assert_eq!(tasks[2].tags(), tasks[1].tags());
+        assert_eq!(tasks[2].parent(), Some(&TaskId::new(1)));
+
+        // Doing it again does not create yet another occurrence
+        assert_eq!(
+            task_list
+                .execute(Command::Do(TaskId::new(2).into()))
+                .unwrap(),
+            Outcome::Updated(succeeded(&[2]))
+        );
+    }
+
+    #[test]
+    fn it_makes_recurring_tasks_due_today_by_default() {
+        let mut task_list = task_list();
+        let mut attributes = Attributes::default();
+        attributes.parse_arg("every:daily").unwrap();
+        task_list
+            .execute(Command::Add(String::from("Water plants"), attributes))
+            .unwrap();
+
+        assert_eq!(list(&mut task_list)[0].due(), Some(&Date::today()));
+    }
+
+    #[test]
+    fn it_records_when_tasks_change() {
+        let mut task_list = task_list();
+        task_list.execute(add("First")).unwrap();
+
+        let task = &list(&mut task_list)[0];
+        let created = *task.created().unwrap();
+        assert_eq!(task.modified(), Some(&created));
+        assert_eq!(task.completed(), None);
+
+        task_list
+            .execute(Command::Do(TaskId::new(1).into()))
+            .unwrap();
+        let task = &list(&mut task_list)[0];
+        assert!(task.completed().is_some_and(|c| *c >= created));
+        assert_eq!(task.modified(), task.completed());
+
+        task_list
+            .execute(Command::UnDo(TaskId::new(1).into()))
+            .unwrap();
+        assert_eq!(list(&mut task_list)[0].completed(), None);
+
+        match task_list.execute(Command::History(TaskId::new(1))).unwrap() {
+            Outcome::Changes(id, changes) => {
+                assert_eq!(id, TaskId::new(1));
+                let summaries: Vec<_> = changes.iter().map(|c| c.summary()).collect();
+                assert_eq!(summaries, vec!["created", "done", "reopened"]);
+                assert!(changes.iter().all(|c| c.at.is_some()));
+            },
+            other => panic!("Unexpected history result {:?}", other),
+        }
+        assert_eq!(
+            task_list.execute(Command::History(TaskId::new(2))),
+            Err(TaskError::UnknownTask(TaskId::new(2)))
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_activity_of_tasks_past_what_can_be_reverted() {
+        let mut task_list = task_list();
+        task_list.execute(add("First")).unwrap();
+        task_list
+            .execute(Command::Do(TaskId::new(1).into()))
+            .unwrap();
+        task_list.execute(Command::Revert).unwrap();
+        for i in 0..120 {
+            task_list.execute(add(&format!("Task {}", i))).unwrap();
+        }
+
+        match task_list.execute(Command::History(TaskId::new(1))).unwrap() {
+            Outcome::Changes(_, changes) => {
+                let summaries: Vec<_> = changes.iter().map(|c| c.summary()).collect();
+                assert_eq!(summaries, vec!["created", "done", "reopened"]);
+            },
+            other => panic!("Unexpected history result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_edits_tasks_in_place() {
+        let mut task_list = task_list();
+        task_list.execute(add("First")).unwrap();
+        task_list.execute(add("Second")).unwrap();
+        task_list
+            .execute(Command::Do(TaskId::new(2).into()))
+            .unwrap();
+
+        let mut attributes = Attributes::default();
+        for arg in ["prio:high", "due:2026-11-01", "#home"] {
+            attributes.parse_arg(arg).unwrap();
+        }
+        let edit = Command::Edit(TaskId::new(2), Some(String::from("Renamed")), attributes);
+        assert_eq!(
+            task_list.execute(edit).unwrap(),
+            Outcome::Updated(succeeded(&[2]))
+        );
+
+        let task = &list(&mut task_list)[1];
+        assert_eq!(task.id(), &TaskId::new(2));
+        assert_eq!(task.text(), "Renamed");
+        assert_eq!(task.status(), &TaskStatus::Done);
+        assert_eq!(task.priority(), &crate::task::Priority::High);
+
+        let mut attributes = Attributes::default();
+        for arg in ["-#home", "prio:none", "due:none", "#work"] {
+            attributes.parse_arg(arg).unwrap();
+        }
+        let edit = Command::Edit(TaskId::new(2), None, attributes);
+        task_list.execute(edit).unwrap();
+        let task = &list(&mut task_list)[1];
+        assert_eq!(task.priority(), &crate::task::Priority::Normal);
+        assert_eq!(task.due(), None);
+        assert_eq!(task.tags().len(), 1);
+        assert!(task.tags().contains("work"));
+
+        let edit = Command::Edit(TaskId::new(7), None, Attributes::default());
+        assert!(matches!(
+            task_list.execute(edit),
+            Err(TaskError::UnknownTask(_))
+        ));
+    }
+
+    #[test]
+    fn it_acts_on_several_tasks_at_once() {
+        let mut task_list = task_list();
+        for text in ["First", "Second", "Third", "Fourth"] {
+            task_list.execute(add(text)).unwrap();
+        }
+
+        let ids = |ids: &[usize]| Selection::Ids(ids.iter().map(|i| TaskId::new(*i)).collect());
+        let mut expected = succeeded(&[1, 3]);
+        expected
+            .failed
+            .push((TaskId::new(9), TaskError::UnknownTask(TaskId::new(9))));
+        assert_eq!(
+            task_list.execute(Command::Do(ids(&[1, 3, 9]))).unwrap(),
+            Outcome::Updated(expected)
+        );
+
+        assert_eq!(
+            task_list
+                .execute(Command::Delete(Selection::Done, false))
+                .unwrap(),
+            Outcome::Deleted(succeeded(&[1, 3]))
+        );
+        assert_eq!(list(&mut task_list).len(), 2);
+        assert_eq!(
+            task_list
+                .execute(Command::Delete(Selection::Done, false))
+                .unwrap(),
+            Outcome::Deleted(succeeded(&[]))
+        );
+
+        task_list.execute(Command::Revert).unwrap();
+        assert_eq!(list(&mut task_list).len(), 4);
+    }
+
+    #[test]
+    fn it_imports_tasks_under_fresh_ids_when_taken() {
+        let dir = std::env::temp_dir();
+        let file = |name: &str| {
+            let path = dir.join(format!("todo_list_{}_{}", std::process::id(), name));
+            String::from(path.to_str().unwrap())
+        };
+        let (markdown, json) = (file("import.md"), file("import.json"));
+        fs::write(&markdown, "- [ ] Plan trip\n  - [x] Book hotel\n- oops\n").unwrap();
+        let content =
+            r#"[{"id": 9, "text": "Pack", "parent": 1, "created": "2025-10-18 09:30:00"}]"#;
+        fs::write(&json, content).unwrap();
+
+        let mut task_list = task_list();
+        task_list.execute(add("First")).unwrap();
+        task_list.execute(add("Second")).unwrap();
+
+        assert_eq!(
+            task_list.execute(Command::Import(markdown.clone())),
+            Ok(Outcome::Imported(vec![TaskId::new(3), TaskId::new(4)]))
+        );
+        assert_eq!(
+            task_list.execute(Command::Import(json.clone())),
+            Ok(Outcome::Imported(vec![TaskId::new(9)]))
+        );
+
+        let tasks = list(&mut task_list);
+        let texts: Vec<_> = tasks.iter().map(|t| t.text()).collect();
+        assert_eq!(
+            texts,
+            vec!["First", "Second", "Plan trip", "Book hotel", "Pack"]
+        );
+        assert_eq!(tasks[3].parent(), Some(&TaskId::new(3)));
+        assert!(tasks[3].completed().is_some());
+        assert_eq!(tasks[4].parent(), None);
+        assert_eq!(
+            tasks[4].created(),
+            Timestamp::parse("2025-10-18 09:30:00").as_ref()
+        );
+
+        task_list.execute(Command::Revert).unwrap();
+        task_list.execute(Command::Revert).unwrap();
+        assert_eq!(list(&mut task_list).len(), 2);
+        assert!(matches!(
+            task_list.execute(Command::Import(file("missing.md"))),
+            Err(TaskError::Import(_))
+        ));
+
+        fs::remove_file(markdown).unwrap();
+        fs::remove_file(json).unwrap();
+    }
+
+    #[test]
+    fn it_refuses_to_add_tasks_once_ids_run_out() {
+        let path =
+            std::env::temp_dir().join(format!("todo_list_{}_last_id.json", std::process::id()));
+        let file = String::from(path.to_str().unwrap());
+        let content = format!(r#"[{{"id": {}, "text": "Last"}}]"#, TaskId::MAX - 1);
+        fs::write(&file, content).unwrap();
+
+        let mut task_list = task_list();
+        assert_eq!(
+            task_list.execute(Command::Import(file.clone())),
+            Ok(Outcome::Imported(vec![TaskId::new(TaskId::MAX - 1)]))
+        );
+        let mut attributes = Attributes::default();
+        attributes.parse_arg("every:daily").unwrap();
+        task_list
+            .execute(Command::Add(String::from("Water plants"), attributes))
+            .unwrap();
+        assert_eq!(list(&mut task_list)[1].id(), &TaskId::new(TaskId::MAX));
+
+        assert_eq!(
+            task_list.execute(add("One more")),
+            Err(TaskError::NoIdsLeft)
+        );
+        assert_eq!(
+            task_list.execute(Command::Do(TaskId::new(TaskId::MAX).into())),
+            Err(TaskError::NoIdsLeft)
+        );
+        assert_eq!(list(&mut task_list).len(), 2);
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn it_reverts_and_redoes_any_command() {
+        let mut task_list = task_list();
+        task_list.execute(add("First")).unwrap();
+        task_list.execute(add("Second")).unwrap();
+        task_list
+            .execute(Command::Delete(TaskId::new(1).into(), false))
+            .unwrap();
+
+        let ids = vec![TaskId::new(1)];
+        assert_eq!(
+            task_list.execute(Command::Revert).unwrap(),
+            Outcome::Reverted(ids.clone())
+        );
+        assert_eq!(list(&mut task_list).len(), 2);
+        assert_eq!(
+            task_list.execute(Command::Redo).unwrap(),
+            Outcome::Redone(ids)
+        );
+        assert_eq!(list(&mut task_list).len(), 1);
+        assert!(matches!(
+            task_list.execute(Command::Redo),
+            Err(TaskError::NothingToRedo)
+        ));
+
+        for _ in 0..3 {
+            task_list.execute(Command::Revert).unwrap();
+        }
+        assert!(list(&mut task_list).is_empty());
+        assert!(matches!(
+            task_list.execute(Command::Revert),
+            Err(TaskError::NothingToRevert)
+        ));
+    }
+
+    #[test]
+    fn it_refuses_to_revert_tasks_changed_since() {
+        let path = std::env::temp_dir().join(format!("todo_list_{}_conflict", std::process::id()));
+        let file = String::from(path.to_str().unwrap());
+        let history = format!("{}.history", file);
+        let _ = fs::remove_file(&file);
+        let _ = fs::remove_file(&history);
+
+        let mut first = TaskList::new(Box::default(), &file)
+            .unwrap()
+            .with_history(History::persistent(&history))
+            .unwrap();
+        first.execute(add("First")).unwrap();
+
+        let mut second = TaskList::new(Box::default(), &file).unwrap();
+        let edit = Command::Edit(
+            TaskId::new(1),
+            Some(String::from("Changed")),
+            Attributes::default(),
+        );
+        second.execute(edit).unwrap();
+
+        assert_eq!(
+            first.execute(Command::Revert),
+            Err(TaskError::Conflict(TaskId::new(1)))
+        );
+        assert_eq!(list(&mut first)[0].text(), "Changed");
+        assert_eq!(first.execute(Command::Redo), Err(TaskError::NothingToRedo));
+
+        fs::remove_file(&file).unwrap();
+        fs::remove_file(&history).unwrap();
+        let _ = fs::remove_file(format!("{}.lock", file));
+    }
+
+    #[test]
+    fn it_executes_several_commands_as_a_single_change() {
+        let mut task_list = task_list();
+        task_list.execute(add("First")).unwrap();
+        task_list.execute(add("Second")).unwrap();
+        task_list
+            .execute(Command::Block(TaskId::new(1), TaskId::new(2)))
+            .unwrap();
+
+        let edit = |text: &str| {
+            Command::Edit(
+                TaskId::new(1),
+                Some(String::from(text)),
+                Attributes::default(),
+            )
+        };
+        let commands = |commands: Vec<Command>| {
+            let mut commands = commands.into_iter();
+            move |_: &[Outcome]| commands.next()
+        };
+        assert_eq!(
+            task_list.execute_all(commands(vec![
+                edit("Renamed"),
+                Command::Do(TaskId::new(1).into())
+            ])),
+            Err(TaskError::Blocked(TaskId::new(1), vec![TaskId::new(2)]))
+        );
+        assert_eq!(list(&mut task_list)[0].text(), "First");
+
+        let steps = vec![
+            edit("Renamed"),
+            Command::Unblock(TaskId::new(1), None),
+            Command::Do(TaskId::new(1).into()),
+        ];
+        assert_eq!(task_list.execute_all(commands(steps)).unwrap().len(), 3);
+        let task = &list(&mut task_list)[0];
+        assert_eq!(task.text(), "Renamed");
+        assert_eq!(task.status(), &TaskStatus::Done);
+
+        task_list.execute(Command::Revert).unwrap();
+        let task = &list(&mut task_list)[0];
+        assert_eq!(task.text(), "First");
+        assert_eq!(task.status(), &TaskStatus::Blocked);
+    }
+
+    #[test]
+    fn it_keeps_what_other_sessions_recorded_when_executing_several_commands() {
+        let path = std::env::temp_dir().join(format!("todo_list_{}_all", std::process::id()));
+        let file = String::from(path.to_str().unwrap());
+        let history = format!("{}.history", file);
+        let _ = fs::remove_file(&file);
+        let _ = fs::remove_file(&history);
+        let session = || {
+            TaskList::new(Box::default(), &file)
+                .unwrap()
+                .with_history(History::persistent(&history))
+                .unwrap()
+        };
+
+        let mut first = session();
+        first.execute(add("First")).unwrap();
+        session().execute(add("Second")).unwrap();
+        let edit = Command::Edit(
+            TaskId::new(1),
+            Some(String::from("Changed")),
+            Attributes::default(),
+        );
+        let mut edit = Some(edit);
+        first.execute_all(|_| edit.take()).unwrap();
+
+        for _ in 0..3 {
+            first.execute(Command::Revert).unwrap();
+        }
+        assert!(list(&mut first).is_empty());
+
+        fs::remove_file(&file).unwrap();
+        fs::remove_file(&history).unwrap();
+        let _ = fs::remove_file(format!("{}.lock", file));
+    }
+
+    #[derive(Debug)]
+    struct ReadOnlyStore;
+
+    impl TaskStore for ReadOnlyStore {
+        fn load(&mut self) -> Result<Snapshot, io::Error> {
+            Ok(Snapshot::default())
+        }
+
+        fn save(&mut self, _: &[&Task], _: TaskId) -> Result<(), io::Error> {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "read only"))
+        }
+
+        fn append(&mut self, _: &Task) -> Result<(), io::Error> {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "read only"))
+        }
+    }
+
+    #[test]
+    fn it_rolls_back_changes_that_could_not_be_persisted() {
+        let mut task_list = TaskList::with_store(Box::default(), Box::new(ReadOnlyStore)).unwrap();
+
+        let result = task_list.execute(add("First"));
+        assert!(matches!(result, Err(TaskError::Io(_))));
+        assert!(result.unwrap_err().source().is_some());
+        assert!(list(&mut task_list).is_empty());
+        assert_eq!(task_list.next_id, 1);
+        assert!(matches!(
+            task_list.execute(Command::Revert),
+            Err(TaskError::NothingToRevert)
+        ));
+    }
+
+    #[test]
+    fn it_only_saves_snapshots_of_unsaved_changes() {
+        let mut task_list = TaskList::with_store(Box::default(), Box::new(ReadOnlyStore)).unwrap();
+
+        list(&mut task_list);
+        assert!(task_list.snapshot().is_ok());
     }
 }
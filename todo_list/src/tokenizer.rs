@@ -0,0 +1,133 @@
+//! Shell-like splitting of a command line into arguments.
+//!
+//! Arguments are separated by whitespace. Single quotes keep everything between
+//! them as is, double quotes do the same except for `\"` and `\\`, and outside
+//! of quotes a backslash keeps the next character as is.
+//! Quoted and unquoted pieces next to each other form a single argument, so
+//! `search:"buy milk"` is one.
+
+use crate::command::BuildError;
+
+/// A single argument of a command line
+#[derive(PartialEq, Eq, Debug)]
+pub struct Token {
+    pub value: String,
+    /// Column the argument starts at, from 1
+    pub column: usize,
+    /// `true` if any part of it was quoted or escaped, so it is always taken
+    /// literally instead of as an attribute
+    pub quoted: bool,
+}
+
+/// Splits the command line into arguments.
+/// Errors point at the column of the quote or backslash left without a match.
+pub fn tokenize(input: &str) -> Result<Vec<Token>, BuildError> {
+    let mut tokens = vec![];
+    let mut current: Option<Token> = None;
+    let mut chars = input.chars().zip(1..);
+
+    while let Some((c, column)) = chars.next() {
+        if c.is_whitespace() {
+            tokens.extend(current.take());
+            continue;
+        }
+
+        let token = current.get_or_insert_with(|| Token {
+            value: String::new(),
+            column,
+            quoted: false,
+        });
+
+        match c {
+            '\\' => {
+                let (escaped, _) = chars.next().ok_or(BuildError::DanglingEscape(column))?;
+                token.value.push(escaped);
+                token.quoted = true;
+            },
+            '\'' | '"' => {
+                token.quoted = true;
+                loop {
+                    match chars.next() {
+                        None => return Err(BuildError::UnclosedQuote(column)),
+                        Some((q, _)) if q == c => break,
+                        Some(('\\', _)) if c == '"' => match chars.next() {
+                            Some((e @ ('"' | '\\'), _)) => token.value.push(e),
+                            Some((e, _)) => {
+                                token.value.push('\\');
+                                token.value.push(e);
+                            },
+                            None => return Err(BuildError::UnclosedQuote(column)),
+                        },
+                        Some((q, _)) => token.value.push(q),
+                    }
+                }
+            },
+            c => token.value.push(c),
+        }
+    }
+    tokens.extend(current);
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn values(input: &str) -> Vec<String> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.value)
+            .collect()
+    }
+
+    #[test]
+    fn it_splits_arguments_on_whitespace() {
+        assert_eq!(values("  add buy\tmilk "), vec!["add", "buy", "milk"]);
+        assert!(values("   ").is_empty());
+
+        let tokens = tokenize("do  3").unwrap();
+        assert_eq!(
+            tokens[1],
+            Token {
+                value: String::from("3"),
+                column: 5,
+                quoted: false,
+            }
+        );
+    }
+
+    #[test]
+    fn it_keeps_quoted_text_together() {
+        assert_eq!(
+            values(r#"add 'input with more words' "it's done" #tag"#),
+            vec!["add", "input with more words", "it's done", "#tag"]
+        );
+        assert_eq!(
+            values(r#"list search:"buy milk" it'"'s"#),
+            vec!["list", "search:buy milk", "it\"s"]
+        );
+        assert_eq!(values("add ''"), vec!["add", ""]);
+        assert!(tokenize("add 'prio:high'").unwrap()[1].quoted);
+    }
+
+    #[test]
+    fn it_unescapes_characters() {
+        assert_eq!(
+            values(r#"add buy\ milk \#home "say \"hi\" \n""#),
+            vec!["add", "buy milk", "#home", "say \"hi\" \\n"]
+        );
+        assert_eq!(values(r"add 'C:\tmp'"), vec!["add", r"C:\tmp"]);
+    }
+
+    #[test]
+    fn it_points_at_unmatched_quotes_and_escapes() {
+        assert_eq!(
+            tokenize("add \"not closed "),
+            Err(BuildError::UnclosedQuote(5))
+        );
+        assert_eq!(tokenize("add it's"), Err(BuildError::UnclosedQuote(7)));
+        assert_eq!(tokenize("add milk\\"), Err(BuildError::DanglingEscape(9)));
+    }
+}